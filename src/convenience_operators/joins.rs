@@ -1,11 +1,12 @@
 use crate::key::Key;
 use crate::monoid::Monoid;
+use crate::shared::{MaybeSend, MaybeSync};
 use crate::tuple::{fst, snd, swap};
-use crate::{Op, Relation};
+use crate::{Op, Relation, StaticTable};
 use std::ops::Mul;
 
 impl<'a, C: Op> Relation<'a, C> {
-    pub fn semijoin_on<F: Fn(&C::D) -> C2::D + 'static, C2: Op, R3: Monoid>(
+    pub fn semijoin_on<F: Fn(&C::D) -> C2::D + MaybeSend + 'static, C2: Op, R3: Monoid>(
         self,
         other: Relation<'a, C2>,
         f: F,
@@ -18,7 +19,7 @@ impl<'a, C: Op> Relation<'a, C> {
             .op_named("semijoin_on")
             .hmap(snd)
     }
-    pub fn antijoin_on<F: Fn(&C::D) -> C2::D + 'static, C2: Op>(
+    pub fn antijoin_on<F: Fn(&C::D) -> C2::D + MaybeSend + 'static, C2: Op>(
         self,
         other: Relation<'a, C2>,
         f: F,
@@ -31,6 +32,27 @@ impl<'a, C: Op> Relation<'a, C> {
             .op_named("antijoin_on")
             .hmap(snd)
     }
+    /// Looks up each record in `table` under the key `f` computes for it, pairing the record with
+    /// every value found there. See `join_static`.
+    pub fn lookup<
+        F: Fn(&C::D) -> K + MaybeSend + 'static,
+        K: Key + MaybeSync,
+        V: Key + MaybeSync,
+        R: Monoid + MaybeSync,
+        R3: Monoid,
+    >(
+        self,
+        table: &StaticTable<K, V, R>,
+        f: F,
+    ) -> Relation<'a, impl Op<D = (C::D, V), R = R3>>
+    where
+        C::R: Mul<R, Output = R3>,
+    {
+        self.hmap(move |val| (f(&val), val))
+            .join_static(table)
+            .op_named("lookup")
+            .hmap(snd)
+    }
     pub fn intersection<C2: Op<D = C::D>, R3: Monoid>(
         self,
         other: Relation<'a, C2>,
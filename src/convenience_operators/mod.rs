@@ -1,17 +1,22 @@
 mod joins;
+mod rows;
+mod weighted;
 
 use crate::core::iter::TupleableWith;
 use crate::key::Key;
 use crate::map::{SingletonMap, UnitMap};
 use crate::monoid::Monoid;
+use crate::shared::{MaybeSend, MaybeSync};
 use crate::{
-    Arrangement, CreationContext, DynOp, ExecutionContext, Input, IsReduce, Op, Receiver,
-    ReduceOutput, Relation,
+    Arrangement, CreationContext, DynOp, ExecutionContext, Input, Interned, Interner, IsReduce, Op,
+    Receiver, ReduceOutput, Relation,
 };
 use std::collections::{BTreeMap, HashMap};
 use std::iter;
 use std::ops::Neg;
 
+pub use self::rows::RowRelation;
+
 pub type DynReceiver<D, R = isize> = Receiver<DynOp<D, R>>;
 pub type Collection<'a, D, R = isize> = Relation<'a, DynReceiver<D, R>>;
 pub type MapMapArrangement<K, V, R = isize> = Arrangement<(K, V), R, HashMap<K, HashMap<V, R>>>;
@@ -29,33 +34,33 @@ impl<'a, C: Op> Relation<'a, C> {
     pub fn collect(self) -> Collection<'a, C::D, C::R> {
         self.dynamic().split()
     }
-    pub fn flat_map<F: Fn(C::D) -> I + 'static, D2: Key, I: IntoIterator<Item = D2>>(
+    pub fn flat_map<F: Fn(C::D) -> I + MaybeSend + 'static, D2: Key, I: IntoIterator<Item = D2>>(
         self,
         f: F,
     ) -> Relation<'a, impl Op<D = D2, R = C::R>> {
         self.flat_map_dr(move |x, r| f(x).into_iter().tuple_with(r))
             .op_named("flat_map")
     }
-    pub fn map<F: Fn(C::D) -> D2 + 'static, D2: Key>(
+    pub fn map<F: Fn(C::D) -> D2 + MaybeSend + 'static, D2: Key>(
         self,
         f: F,
     ) -> Relation<'a, impl Op<D = D2, R = C::R>> {
         self.flat_map(move |x| iter::once(f(x))).op_named("map")
     }
-    pub fn hmap<F: Fn(C::D) -> D2 + 'static, D2: Key>(
+    pub fn hmap<F: Fn(C::D) -> D2 + MaybeSend + 'static, D2: Key>(
         self,
         f: F,
     ) -> Relation<'a, impl Op<D = D2, R = C::R>> {
         self.map(f).hidden()
     }
-    pub fn filter<F: Fn(&C::D) -> bool + 'static>(
+    pub fn filter<F: Fn(&C::D) -> bool + MaybeSend + 'static>(
         self,
         f: F,
     ) -> Relation<'a, impl Op<D = C::D, R = C::R>> {
         self.flat_map(move |x| if f(&x) { Some(x) } else { None })
             .op_named("filter")
     }
-    pub fn map_r<F: Fn(C::R) -> R2 + 'static, R2: Monoid>(
+    pub fn map_r<F: Fn(C::R) -> R2 + MaybeSend + 'static, R2: Monoid>(
         self,
         f: F,
     ) -> Relation<'a, impl Op<D = C::D, R = R2>> {
@@ -112,6 +117,23 @@ impl<'a, C: Op> Relation<'a, C> {
     }
 }
 
+impl<'a, C: Op> Relation<'a, C>
+where
+    C::R: PartialOrd + MaybeSend,
+{
+    /// Keeps each record whose multiplicity is positive, with multiplicity one. Unlike `distinct`,
+    /// records with negative multiplicities are dropped rather than kept.
+    pub fn positive(self) -> Relation<'a, impl Op<D = C::D, R = isize>> {
+        self.threshold(|r| (*r > C::R::default()) as isize)
+            .op_named("positive")
+    }
+    /// Keeps each record whose multiplicity is at least `n`, with multiplicity one.
+    pub fn at_least(self, n: C::R) -> Relation<'a, impl Op<D = C::D, R = isize>> {
+        self.threshold(move |r| (*r >= n) as isize)
+            .op_named("at_least")
+    }
+}
+
 impl<'a, C: Op<R = isize>> Relation<'a, C> {
     pub fn hist_including<C2: Op<D = C::D, R = isize>>(
         self,
@@ -150,6 +172,34 @@ impl<'a, K: Key, V: Key, C: Op<D = (K, V)>> Relation<'a, C> {
         })
         .op_named("group_max")
     }
+    /// The value in each group whose projection under `f` is smallest, or the smallest such value
+    /// if several tie.
+    pub fn group_min_by_key<O: Ord, F: Fn(&V) -> O + MaybeSend + 'static>(
+        self,
+        f: F,
+    ) -> Relation<'a, impl Op<D = C::D, R = isize> + IsReduce<K = K, M = SingletonMap<V>>>
+    where
+        V: Ord,
+    {
+        self.reduce(move |_, xs: &HashMap<V, C::R>| {
+            SingletonMap(xs.keys().min_by_key(|v| (f(v), *v)).unwrap().clone())
+        })
+        .op_named("group_min_by_key")
+    }
+    /// The value in each group whose projection under `f` is largest, or the largest such value
+    /// if several tie.
+    pub fn group_max_by_key<O: Ord, F: Fn(&V) -> O + MaybeSend + 'static>(
+        self,
+        f: F,
+    ) -> Relation<'a, impl Op<D = C::D, R = isize> + IsReduce<K = K, M = SingletonMap<V>>>
+    where
+        V: Ord,
+    {
+        self.reduce(move |_, xs: &HashMap<V, C::R>| {
+            SingletonMap(xs.keys().max_by_key(|v| (f(v), *v)).unwrap().clone())
+        })
+        .op_named("group_max_by_key")
+    }
 }
 
 impl<K: Key, V: Key, C: Op<D = (K, V), R = isize>> Relation<'static, C> {
@@ -177,6 +227,40 @@ impl<K: Key, V: Key, C: Op<D = (K, V), R = isize>> Relation<'static, C> {
     }
 }
 
+impl<'a, C: Op> Relation<'a, C>
+where
+    C::D: MaybeSync,
+{
+    /// Replaces every record with its handle from `interner`, so that downstream operators clone
+    /// and compare handles rather than the records themselves.
+    pub fn intern(
+        self,
+        interner: &Interner<C::D>,
+    ) -> Relation<'a, impl Op<D = Interned<C::D>, R = C::R>> {
+        let interner = interner.clone();
+        self.map(move |x| interner.intern(x)).op_named("intern")
+    }
+}
+
+impl<'a, K: Key + MaybeSync, V: Key, C: Op<D = (K, V)>> Relation<'a, C> {
+    /// Like `intern`, but only interns the keys.
+    pub fn intern_keys(
+        self,
+        interner: &Interner<K>,
+    ) -> Relation<'a, impl Op<D = (Interned<K>, V), R = C::R>> {
+        let interner = interner.clone();
+        self.map(move |(k, v)| (interner.intern(k), v))
+            .op_named("intern_keys")
+    }
+}
+
+impl<'a, T: Key + MaybeSync, C: Op<D = Interned<T>>> Relation<'a, C> {
+    /// Replaces every handle with a copy of the value it refers to.
+    pub fn unintern(self) -> Relation<'a, impl Op<D = T, R = C::R>> {
+        self.map(|x| T::clone(&x)).op_named("unintern")
+    }
+}
+
 impl<D: Key> Input<D> {
     pub fn insert(&self, context: &ExecutionContext, x: D) {
         self.update(context, x, 1)
@@ -0,0 +1,70 @@
+use crate::monoid::Monoid;
+use crate::row::{Row, Value};
+use crate::shared::MaybeSend;
+use crate::{DynOp, Op, Relation};
+use std::ops::Mul;
+
+pub type RowRelation<'a, R = isize> = Relation<'a, DynOp<Row, R>>;
+
+impl<'a, C: Op<D = Row>> Relation<'a, C> {
+    /// Keeps only the given columns (in the given order).
+    pub fn project(self, cols: Vec<usize>) -> RowRelation<'a, C::R> {
+        self.map(move |row| row.project(&cols))
+            .op_named("project")
+            .dynamic()
+    }
+    /// Keeps only the rows whose value in column `col` satisfies `f`.
+    pub fn select<F: Fn(&Value) -> bool + MaybeSend + 'static>(
+        self,
+        col: usize,
+        f: F,
+    ) -> RowRelation<'a, C::R> {
+        self.filter(move |row| f(&row[col]))
+            .op_named("select")
+            .dynamic()
+    }
+    /// Keeps only the rows whose value in column `col` is `value`.
+    pub fn select_eq(self, col: usize, value: Value) -> RowRelation<'a, C::R> {
+        self.select(col, move |v| v == &value).op_named("select_eq")
+    }
+    /// Keeps only the rows whose values in columns `col1` and `col2` are equal.
+    pub fn select_cols_eq(self, col1: usize, col2: usize) -> RowRelation<'a, C::R> {
+        self.filter(move |row| row[col1] == row[col2])
+            .op_named("select_cols_eq")
+            .dynamic()
+    }
+    /// Joins rows for which the values in `cols` match the values in `other_cols` of the rows of
+    /// `other`. The output rows consist of all the columns of the left row followed by all the
+    /// columns of the right row.
+    pub fn join_on_columns<C2: Op<D = Row>, OR: Monoid>(
+        self,
+        other: Relation<'a, C2>,
+        cols: Vec<usize>,
+        other_cols: Vec<usize>,
+    ) -> RowRelation<'a, OR>
+    where
+        C::R: Mul<C2::R, Output = OR>,
+    {
+        assert_eq!(cols.len(), other_cols.len(), "Mismatched join columns");
+        self.hmap(move |row| (row.project(&cols), row))
+            .join(other.hmap(move |row| (row.project(&other_cols), row)))
+            .op_named("join_on_columns")
+            .hmap(|(_, (l, r))| l.concat(r))
+            .dynamic()
+    }
+    /// Keeps only the rows whose values in `cols` don't match the values in `other_cols` of any
+    /// row of `other`.
+    pub fn antijoin_on_columns<C2: Op<D = Row>>(
+        self,
+        other: Relation<'a, C2>,
+        cols: Vec<usize>,
+        other_cols: Vec<usize>,
+    ) -> RowRelation<'a, C::R> {
+        assert_eq!(cols.len(), other_cols.len(), "Mismatched join columns");
+        self.hmap(move |row| (row.project(&cols), row))
+            .antijoin(other.hmap(move |row| row.project(&other_cols)))
+            .op_named("antijoin_on_columns")
+            .hmap(|(_, row)| row)
+            .dynamic()
+    }
+}
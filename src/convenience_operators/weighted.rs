@@ -0,0 +1,38 @@
+use crate::key::Key;
+use crate::map::OptionMap;
+use crate::semiring::Semiring;
+use crate::{Op, Relation};
+use std::collections::HashMap;
+
+impl<'a, D: Key, W: Semiring, C: Op<D = (D, W), R = isize>> Relation<'a, C> {
+    /// Treats this relation as a relation of records weighted by `W`, and replaces the weights of
+    /// each record with their semiring sum. Records whose weights sum to `W::zero()` are dropped.
+    pub fn semiring_sum(self) -> Relation<'a, impl Op<D = (D, W), R = isize>> {
+        self.reduce(|_, weights: &HashMap<W, isize>| {
+            let sum = weights
+                .iter()
+                .filter(|&(_, &r)| r > 0)
+                .fold(W::zero(), |acc, (w, &r)| acc.plus(&w.plus_n(r as usize)));
+            if sum == W::zero() {
+                OptionMap::default()
+            } else {
+                OptionMap::new(sum, 1)
+            }
+        })
+        .op_named("semiring_sum")
+    }
+}
+
+impl<'a, K: Key, V: Key, W: Semiring, C: Op<D = (K, (V, W)), R = isize>> Relation<'a, C> {
+    /// Joins two relations of weighted records, combining the weights of each pair with the
+    /// semiring product. Follow with `semiring_sum` to combine the weights of each output record.
+    #[allow(clippy::type_complexity)]
+    pub fn join_weighted<V2: Key, C2: Op<D = (K, (V2, W)), R = isize>>(
+        self,
+        other: Relation<'a, C2>,
+    ) -> Relation<'a, impl Op<D = ((K, (V, V2)), W), R = isize>> {
+        self.join(other)
+            .map(|(k, ((v, lw), (v2, rw)))| ((k, (v, v2)), lw.times(&rw)))
+            .op_named("join_weighted")
+    }
+}
@@ -1,8 +1,10 @@
 use super::{ContextId, CreationContext, ExecutionContext, Relation, Step};
 use crate::core::is_map::IsAddMap;
 use crate::core::node::Node;
+#[cfg(feature = "sync")]
+use crate::core::node::NodeInfo;
 use crate::core::operator::{DynOp, Op};
-use std::cell::{Ref, RefCell};
+use crate::core::shared::{Lock, MaybeSend, Ref, Shared};
 use std::collections::HashMap;
 
 pub struct Arrangement<
@@ -11,7 +13,7 @@ pub struct Arrangement<
     M: IsAddMap<D, R> = HashMap<D, R>,
     C: Op<D = D, R = R> = DynOp<D, R>,
 > {
-    inner: RefCell<ArrangementInner<D, R, M, C>>,
+    inner: Shared<Lock<ArrangementInner<D, R, M, C>>>,
     context_id: ContextId,
 }
 
@@ -21,6 +23,10 @@ impl<C: Op, M: IsAddMap<C::D, C::R>> Arrangement<C::D, C::R, M, C> {
         self.inner.borrow_mut().flow(context.step);
         Ref::map(self.inner.borrow(), |i| &i.value)
     }
+    #[cfg(feature = "sync")]
+    pub(super) fn node_info(&self) -> Shared<Lock<NodeInfo>> {
+        Shared::clone(&self.inner.borrow().from.info)
+    }
 }
 
 struct ArrangementInner<D, R, M: IsAddMap<D, R>, C: Op<D = D, R = R>> {
@@ -30,7 +36,7 @@ struct ArrangementInner<D, R, M: IsAddMap<D, R>, C: Op<D = D, R = R>> {
 }
 
 impl<C: Op, M: IsAddMap<C::D, C::R>> ArrangementInner<C::D, C::R, M, C> {
-    fn flow<'a>(&'a mut self, step: usize) {
+    fn flow(&mut self, step: usize) {
         let ArrangementInner {
             ref mut from,
             ref mut value,
@@ -44,17 +50,25 @@ impl<C: Op, M: IsAddMap<C::D, C::R>> ArrangementInner<C::D, C::R, M, C> {
 }
 
 impl<C: Op> Relation<'static, C> {
-    pub fn get_arrangement<M: IsAddMap<C::D, C::R>>(
+    pub fn get_arrangement<M: IsAddMap<C::D, C::R> + MaybeSend + 'static>(
         self,
         context: &CreationContext,
     ) -> Arrangement<C::D, C::R, M, C> {
         assert_eq!(self.context_id, context.context_id, "Context mismatch");
+        let inner = Shared::new(Lock::new(ArrangementInner {
+            from: self.inner,
+            step: 0,
+            value: M::default(),
+        }));
+        let weak = Shared::downgrade(&inner);
+        context.add_compactor(Box::new(move || {
+            weak.upgrade().map_or(0, |inner| {
+                let ArrangementInner { from, value, .. } = &mut *inner.borrow_mut();
+                from.compact() + value.compact()
+            })
+        }));
         Arrangement {
-            inner: RefCell::new(ArrangementInner {
-                from: self.inner,
-                step: 0,
-                value: Default::default(),
-            }),
+            inner,
             context_id: self.context_id,
         }
     }
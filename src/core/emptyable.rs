@@ -1,4 +1,5 @@
 use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
 
 pub trait Emptyable: Default {
     fn is_empty(&self) -> bool;
@@ -10,14 +11,22 @@ impl<K: Ord, V> Emptyable for BTreeMap<K, V> {
     }
 }
 
-impl<K, V> Emptyable for HashMap<K, V> {
+impl<K, V, S: BuildHasher + Default> Emptyable for HashMap<K, V, S> {
     fn is_empty(&self) -> bool {
         self.is_empty()
     }
 }
 
-impl Emptyable for isize {
-    fn is_empty(&self) -> bool {
-        *self == 0
-    }
+macro_rules! int_emptyable {
+    ($($t:ty),*) => {
+        $(
+            impl Emptyable for $t {
+                fn is_empty(&self) -> bool {
+                    *self == 0
+                }
+            }
+        )*
+    };
 }
+
+int_emptyable!(isize, i8, i16, i32, i64, i128);
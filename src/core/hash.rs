@@ -0,0 +1,75 @@
+//! The hash maps used for operator state. By default these are `std::collections::HashMap` with
+//! its usual randomly seeded SipHash. With the `fxhash` feature they instead use `FxHasher`, which
+//! is much faster for small keys such as integers but offers no protection against adversarial
+//! inputs. Maps which are only handed out (arrangements, reduce outputs and so on) always use the
+//! standard hasher. The exceptions are `Input::contents` and `StaticTable::get`, which operators
+//! also iterate over, so their hasher type follows these features.
+//!
+//! With the `deterministic` feature, `FxHasher` is used as well. It's implemented here (unlike
+//! `std`'s `DefaultHasher`, whose algorithm may change between Rust releases) and always starts
+//! from the same state, so the layout of every map, and so the order in which operators iterate
+//! over their state and emit records, depends only on the sequence of updates. That order is the
+//! same from one run to the next, and across toolchains as long as `std`'s hash table keeps its
+//! layout.
+
+#[cfg(not(any(feature = "fxhash", feature = "deterministic")))]
+pub type HashBuilder = std::collections::hash_map::RandomState;
+#[cfg(any(feature = "fxhash", feature = "deterministic"))]
+pub type HashBuilder = std::hash::BuildHasherDefault<fx::FxHasher>;
+
+pub type HashMap<K, V> = std::collections::HashMap<K, V, HashBuilder>;
+pub type HashSet<T> = std::collections::HashSet<T, HashBuilder>;
+
+#[cfg(any(feature = "fxhash", feature = "deterministic"))]
+mod fx {
+    use std::hash::Hasher;
+
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    /// The hash function used by the Rust compiler (and Firefox): a multiply and a rotate per word.
+    #[derive(Clone, Copy, Default)]
+    pub struct FxHasher {
+        hash: u64,
+    }
+
+    impl FxHasher {
+        fn add_to_hash(&mut self, word: u64) {
+            self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+        }
+    }
+
+    impl Hasher for FxHasher {
+        fn write(&mut self, bytes: &[u8]) {
+            let mut chunks = bytes.chunks_exact(8);
+            for chunk in &mut chunks {
+                let mut word = [0; 8];
+                word.copy_from_slice(chunk);
+                self.add_to_hash(u64::from_le_bytes(word));
+            }
+            let rest = chunks.remainder();
+            if !rest.is_empty() {
+                let mut word = [0; 8];
+                word[..rest.len()].copy_from_slice(rest);
+                self.add_to_hash(u64::from_le_bytes(word));
+            }
+        }
+        fn write_u8(&mut self, i: u8) {
+            self.add_to_hash(i as u64)
+        }
+        fn write_u16(&mut self, i: u16) {
+            self.add_to_hash(i as u64)
+        }
+        fn write_u32(&mut self, i: u32) {
+            self.add_to_hash(i as u64)
+        }
+        fn write_u64(&mut self, i: u64) {
+            self.add_to_hash(i)
+        }
+        fn write_usize(&mut self, i: usize) {
+            self.add_to_hash(i as u64)
+        }
+        fn finish(&self) -> u64 {
+            self.hash
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Interned values. Cloning, comparing and hashing an `Interned<T>` only touches a pointer and an
+//! id, so large records (such as `String`s) can be used as keys without being deep-cloned for
+//! every tuple an operator emits.
+
+use crate::core::hash::{HashBuilder, HashMap};
+use crate::core::shared::{Lock, MaybeSend, MaybeSync, Shared};
+use std::any::{Any, TypeId};
+use std::cmp::Ordering;
+use std::collections::HashMap as TypeMap;
+use std::fmt::{self, Debug, Display};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ops::Deref;
+
+/// A handle to a value stored in an `Interner`. Two handles from the same interner are equal
+/// exactly when their values are. Handles from different interners are never equal, so don't mix
+/// them.
+pub struct Interned<T> {
+    id: u64,
+    value: Shared<T>,
+}
+
+impl<T> Clone for Interned<T> {
+    fn clone(&self) -> Self {
+        Interned {
+            id: self.id,
+            value: Shared::clone(&self.value),
+        }
+    }
+}
+
+impl<T> PartialEq for Interned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Shared::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl<T> Eq for Interned<T> {}
+
+impl<T> Hash for Interned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+/// Orders by value (unlike equality and hashing, this does look at the values).
+impl<T: Ord> Ord for Interned<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value
+            .cmp(&other.value)
+            .then_with(|| Shared::as_ptr(&self.value).cmp(&Shared::as_ptr(&other.value)))
+    }
+}
+
+impl<T: Ord> PartialOrd for Interned<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Deref for Interned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Debug> Debug for Interned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&*self.value, f)
+    }
+}
+
+impl<T: Display> Display for Interned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&*self.value, f)
+    }
+}
+
+struct InternerInner<T> {
+    hasher: HashBuilder,
+    buckets: HashMap<u64, Vec<Interned<T>>>,
+    next_id: u64,
+}
+
+/// Hands out one `Interned` handle per distinct value. Clones share the same table. Normally
+/// obtained from `CreationContext::interner` or `ExecutionContext::interner` so that every part
+/// of the graph uses the same interner for a given type.
+pub struct Interner<T> {
+    inner: Shared<Lock<InternerInner<T>>>,
+}
+
+impl<T> Clone for Interner<T> {
+    fn clone(&self) -> Self {
+        Interner {
+            inner: Shared::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: Eq + Hash> Interner<T> {
+    pub fn new() -> Self {
+        Interner {
+            inner: Shared::new(Lock::new(InternerInner {
+                hasher: HashBuilder::default(),
+                buckets: HashMap::default(),
+                next_id: 0,
+            })),
+        }
+    }
+    pub fn intern(&self, value: T) -> Interned<T> {
+        let mut inner = self.inner.borrow_mut();
+        let InternerInner {
+            hasher,
+            buckets,
+            next_id,
+        } = &mut *inner;
+        let bucket = buckets.entry(hasher.hash_one(&value)).or_default();
+        if let Some(existing) = bucket.iter().find(|x| *x.value == value) {
+            return existing.clone();
+        }
+        let interned = Interned {
+            id: *next_id,
+            value: Shared::new(value),
+        };
+        *next_id += 1;
+        bucket.push(interned.clone());
+        interned
+    }
+    /// The number of distinct values currently held.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().buckets.values().map(Vec::len).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Forgets every value for which there are no handles left outside the interner, returning
+    /// how many were forgotten. Called by `ExecutionContext::compact`.
+    pub fn purge(&self) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        let mut purged = 0;
+        inner.buckets.retain(|_, bucket| {
+            let before = bucket.len();
+            bucket.retain(|x| Shared::strong_count(&x.value) > 1);
+            purged += before - bucket.len();
+            !bucket.is_empty()
+        });
+        purged
+    }
+}
+
+impl<T: Eq + Hash> Default for Interner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+trait AnyInterner: MaybeSend {
+    fn as_any(&self) -> &dyn Any;
+    fn purge(&self) -> usize;
+}
+
+impl<T: Eq + Hash + MaybeSend + MaybeSync + 'static> AnyInterner for Interner<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn purge(&self) -> usize {
+        Interner::purge(self)
+    }
+}
+
+/// The interners belonging to a context, one per type.
+pub(super) struct Interners(Lock<TypeMap<TypeId, Box<dyn AnyInterner>>>);
+
+impl Interners {
+    pub(super) fn new() -> Self {
+        Interners(Lock::new(TypeMap::new()))
+    }
+    pub(super) fn get<T: Eq + Hash + MaybeSend + MaybeSync + 'static>(&self) -> Interner<T> {
+        self.0
+            .borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Interner::<T>::new()))
+            .as_any()
+            .downcast_ref::<Interner<T>>()
+            .unwrap()
+            .clone()
+    }
+    pub(super) fn purge(&self) -> usize {
+        self.0.borrow().values().map(|i| i.purge()).sum()
+    }
+}
@@ -1,7 +1,7 @@
 use crate::core::emptyable::Emptyable;
 use crate::core::monoid::Monoid;
 use std::collections::{btree_map, hash_map, BTreeMap, HashMap};
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
 
 pub trait IsRemoveMap<K, V>: Emptyable {
     fn remove(&mut self, k: &K) -> Option<V>;
@@ -21,15 +21,27 @@ pub trait IsMap<K, V> {
 
 pub trait IsAddMap<K, V>: Emptyable {
     fn add(&mut self, k: K, v: V);
+    /// Releases any spare capacity (including that of nested maps), returning the number of
+    /// entries' worth which was released.
+    fn compact(&mut self) -> usize {
+        0
+    }
+}
+
+/// Shrinks `map` to fit its contents, returning the number of entries' worth of capacity released.
+pub(crate) fn shrink<K: Eq + Hash, V, S: BuildHasher>(map: &mut HashMap<K, V, S>) -> usize {
+    let before = map.capacity();
+    map.shrink_to_fit();
+    before - map.capacity()
 }
 
-impl<K: Eq + Hash, V> IsRemoveMap<K, V> for HashMap<K, V> {
+impl<K: Eq + Hash, V, S: BuildHasher + Default> IsRemoveMap<K, V> for HashMap<K, V, S> {
     fn remove(&mut self, k: &K) -> Option<V> {
         self.remove(k)
     }
 }
 
-impl<K: Eq + Hash, V> IsMap<K, V> for HashMap<K, V> {
+impl<K: Eq + Hash, V, S: BuildHasher + Default> IsMap<K, V> for HashMap<K, V, S> {
     type Discardable = Self;
 
     fn foreach<F: FnMut(&K, &V)>(&self, mut op: F) {
@@ -41,14 +53,14 @@ impl<K: Eq + Hash, V> IsMap<K, V> for HashMap<K, V> {
         self
     }
 }
-impl<K: Eq + Hash, V> IsDiscardMap<K, V> for HashMap<K, V> {
+impl<K: Eq + Hash, V, S: BuildHasher + Default> IsDiscardMap<K, V> for HashMap<K, V, S> {
     type Iter = hash_map::IntoIter<K, V>;
 
     fn into_iter(self) -> Self::Iter {
         IntoIterator::into_iter(self)
     }
 }
-impl<K: Eq + Hash, V: Monoid> IsAddMap<K, V> for HashMap<K, V> {
+impl<K: Eq + Hash, V: Monoid, S: BuildHasher + Default> IsAddMap<K, V> for HashMap<K, V, S> {
     fn add(&mut self, k: K, v: V) {
         let e = self.entry(k);
         match e {
@@ -69,10 +81,13 @@ impl<K: Eq + Hash, V: Monoid> IsAddMap<K, V> for HashMap<K, V> {
             }
         }
     }
+    fn compact(&mut self) -> usize {
+        shrink(self)
+    }
 }
 
-impl<K1: Eq + Hash, K2, V: Monoid, M2: IsAddMap<K2, V> + Emptyable> IsAddMap<(K1, K2), V>
-    for HashMap<K1, M2>
+impl<K1: Eq + Hash, K2, V: Monoid, M2: IsAddMap<K2, V> + Emptyable, S: BuildHasher + Default>
+    IsAddMap<(K1, K2), V> for HashMap<K1, M2, S>
 {
     fn add(&mut self, (k1, k2): (K1, K2), v: V) {
         if v.is_zero() {
@@ -93,6 +108,10 @@ impl<K1: Eq + Hash, K2, V: Monoid, M2: IsAddMap<K2, V> + Emptyable> IsAddMap<(K1
             }
         }
     }
+    fn compact(&mut self) -> usize {
+        let nested: usize = self.values_mut().map(|m| m.compact()).sum();
+        nested + shrink(self)
+    }
 }
 
 impl<K1: Ord, K2, V: Monoid, M2: IsAddMap<K2, V> + Emptyable> IsAddMap<(K1, K2), V>
@@ -117,4 +136,7 @@ impl<K1: Ord, K2, V: Monoid, M2: IsAddMap<K2, V> + Emptyable> IsAddMap<(K1, K2),
             }
         }
     }
+    fn compact(&mut self) -> usize {
+        self.values_mut().map(|m| m.compact()).sum()
+    }
 }
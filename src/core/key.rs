@@ -1,5 +1,30 @@
+use crate::core::shared::MaybeSend;
 use std::hash::Hash;
 
-pub trait Key: Clone + Eq + Hash + 'static {}
+pub trait Key: Clone + Eq + Hash + MaybeSend + 'static {}
 
-impl<K: Clone + Eq + Hash + 'static> Key for K {}
+impl<K: Clone + Eq + Hash + MaybeSend + 'static> Key for K {}
+
+/// Keys whose sums and differences clamp to the type's minimum and maximum instead of
+/// overflowing. `Relation::band_join` uses these to compute the range of matching keys.
+pub trait SaturatingKey: Key + Ord {
+    fn saturating_add(self, other: Self) -> Self;
+    fn saturating_sub(self, other: Self) -> Self;
+}
+
+macro_rules! impl_saturating_key {
+    ($($t:ty),*) => {
+        $(
+            impl SaturatingKey for $t {
+                fn saturating_add(self, other: Self) -> Self {
+                    <$t>::saturating_add(self, other)
+                }
+                fn saturating_sub(self, other: Self) -> Self {
+                    <$t>::saturating_sub(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_saturating_key!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
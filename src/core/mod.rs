@@ -1,22 +1,36 @@
 mod arrangement;
 pub mod borrow;
 pub mod emptyable;
+pub(crate) mod hash;
+mod intern;
 pub mod is_map;
 pub mod iter;
 pub mod key;
 pub mod monoid;
 mod node;
 mod operator;
+#[cfg(feature = "sync")]
+mod parallel;
+pub mod semiring;
+pub mod shared;
 
 pub use self::arrangement::Arrangement;
+use self::intern::Interners;
+pub use self::intern::{Interned, Interner};
 use self::node::{Node, NodeInfo, NodeMaker};
-pub use self::operator::{subgraph, DynOp, Input, IsReduce, Op, Receiver, ReduceOutput};
+pub use self::operator::{
+    subgraph, Arranged, DynOp, Input, InputCheck, IsReduce, NegativeMultiplicity, Op, Receiver,
+    ReduceOutput, StaticTable,
+};
+#[cfg(feature = "sync")]
+pub use self::parallel::{Flush, Upstream};
+use self::shared::{Lock, MaybeSend, MaybeSync, Shared, Weak};
 use std::{
-    cell::RefCell,
+    cell::Cell,
+    hash::Hash,
     io::{self, Write},
     marker::PhantomData,
     mem,
-    rc::{Rc, Weak},
     sync::atomic,
     sync::atomic::AtomicUsize,
 };
@@ -29,9 +43,30 @@ fn next_id() -> ContextId {
 
 type ContextId = usize;
 
+static NEXT_COMPACTION_PASS: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    static COMPACTION_PASS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Identifies the `ExecutionContext::compact` call running on this thread, so that nodes reachable
+/// from several outputs are only compacted once per call.
+fn compaction_pass() -> usize {
+    COMPACTION_PASS.with(Cell::get)
+}
+
+/// Releases the spare capacity held by one output and everything upstream of it. See
+/// `ExecutionContext::compact`.
+#[cfg(not(feature = "sync"))]
+type Compactor = Box<dyn Fn() -> usize>;
+#[cfg(feature = "sync")]
+type Compactor = Box<dyn Fn() -> usize + Send + Sync>;
+
 pub struct CreationContext {
     context_id: ContextId,
     node_maker: NodeMaker,
+    compactors: Lock<Vec<Compactor>>,
+    interners: Interners,
 }
 
 impl CreationContext {
@@ -39,23 +74,46 @@ impl CreationContext {
         CreationContext {
             context_id: next_id(),
             node_maker: NodeMaker::new(),
+            compactors: Lock::new(Vec::new()),
+            interners: Interners::new(),
         }
     }
+    /// Returns this context's interner for `T`, creating it if necessary. The `ExecutionContext`
+    /// returned by `begin` hands out the same interners.
+    pub fn interner<T: Eq + Hash + MaybeSend + MaybeSync + 'static>(&self) -> Interner<T> {
+        self.interners.get()
+    }
+    fn add_compactor(&self, compactor: Compactor) {
+        self.compactors.borrow_mut().push(compactor)
+    }
+}
+
+/// What `ExecutionContext::compact` freed up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Compaction {
+    /// The number of entries' worth of map capacity released.
+    pub released: usize,
+    /// The number of interned values forgotten.
+    pub forgotten: usize,
 }
 
 pub struct ExecutionContext {
     step: usize,
     context_id: ContextId,
-    infos: Vec<Rc<RefCell<NodeInfo>>>,
+    infos: Vec<Shared<Lock<NodeInfo>>>,
+    compactors: Vec<Compactor>,
+    interners: Interners,
 }
 
 impl CreationContext {
     pub fn begin(self) -> ExecutionContext {
-        let infos: Vec<Rc<RefCell<NodeInfo>>> = mem::take(&mut self.node_maker.infos.borrow_mut());
+        let infos: Vec<Shared<Lock<NodeInfo>>> = mem::take(&mut self.node_maker.infos.borrow_mut());
         ExecutionContext {
             step: 0,
             context_id: self.context_id,
             infos,
+            compactors: self.compactors.into_inner(),
+            interners: self.interners,
         }
     }
 }
@@ -64,6 +122,24 @@ impl ExecutionContext {
     pub fn commit(&mut self) {
         self.step += 1;
     }
+    /// Shrinks the maps held by every operator upstream of an arrangement or reduce output to fit
+    /// their current contents. Useful after retracting a large part of a relation, since maps
+    /// otherwise keep their peak capacity. Also forgets interned values which are no longer
+    /// referenced anywhere.
+    pub fn compact(&self) -> Compaction {
+        let pass = NEXT_COMPACTION_PASS.fetch_add(1, atomic::Ordering::SeqCst);
+        let outer = COMPACTION_PASS.with(|current| current.replace(pass));
+        let released: usize = self.compactors.iter().map(|compactor| compactor()).sum();
+        COMPACTION_PASS.with(|current| current.set(outer));
+        Compaction {
+            released,
+            forgotten: self.interners.purge(),
+        }
+    }
+    /// Returns this context's interner for `T`, creating it if necessary.
+    pub fn interner<T: Eq + Hash + MaybeSend + MaybeSync + 'static>(&self) -> Interner<T> {
+        self.interners.get()
+    }
     pub fn dump_dot<W: Write>(&self, file: &mut W) -> Result<(), io::Error> {
         writeln!(file, "digraph flow {{")?;
         for info_ref in self.infos.iter() {
@@ -176,7 +252,7 @@ pub struct Relation<'a, C: ?Sized> {
 
 struct Dep {
     context_id: usize,
-    node_info: Weak<RefCell<NodeInfo>>,
+    node_info: Weak<Lock<NodeInfo>>,
     node_maker: NodeMaker,
 }
 
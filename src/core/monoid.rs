@@ -1,8 +1,15 @@
 use crate::core::emptyable::Emptyable;
+use crate::core::shared::MaybeSend;
 use std::ops::{AddAssign, Neg, Sub};
 
 pub trait Monoid:
-    Clone + Emptyable + AddAssign<Self> + Sub<Self, Output = Self> + Neg<Output = Self> + 'static
+    Clone
+    + Emptyable
+    + AddAssign<Self>
+    + Sub<Self, Output = Self>
+    + Neg<Output = Self>
+    + MaybeSend
+    + 'static
 {
     fn is_zero(&self) -> bool {
         self.is_empty()
@@ -15,6 +22,7 @@ impl<
             + AddAssign<Self>
             + Sub<Self, Output = Self>
             + Neg<Output = Self>
+            + MaybeSend
             + 'static,
     > Monoid for R
 {
@@ -1,12 +1,9 @@
+use crate::core::shared::{Lock, Shared, Weak};
 use crate::core::{operator::Op, Relation, Step};
-use std::{
-    cell::RefCell,
-    rc::{Rc, Weak},
-};
 
 #[derive(Clone)]
 pub(super) struct Node<C: ?Sized> {
-    pub(super) info: Rc<RefCell<NodeInfo>>,
+    pub(super) info: Shared<Lock<NodeInfo>>,
     pub(super) inner: C,
 }
 
@@ -24,6 +21,9 @@ impl<C: Op> Node<C> {
             info.borrow_mut().message_count += 1;
         })
     }
+    pub(super) fn compact(&mut self) -> usize {
+        self.inner.compact()
+    }
     pub(super) fn as_registrar(self) -> Self {
         self.info.borrow_mut().is_registrar = true;
         self
@@ -45,7 +45,7 @@ pub(super) struct NodeInfo {
     pub(super) shown: bool,
     pub(super) message_count: usize,
     pub(super) relation_id: RelationId,
-    pub(super) deps: Vec<Weak<RefCell<NodeInfo>>>,
+    pub(super) deps: Vec<Weak<Lock<NodeInfo>>>,
     pub(super) hideable: bool,
     pub(super) is_registrar: bool,
     pub(super) depth: usize,
@@ -82,23 +82,23 @@ impl NodeInfo {
 
 #[derive(Clone)]
 pub struct NodeMaker {
-    pub(super) infos: Rc<RefCell<Vec<Rc<RefCell<NodeInfo>>>>>,
+    pub(super) infos: Shared<Lock<Vec<Shared<Lock<NodeInfo>>>>>,
 }
 
 impl NodeMaker {
     pub(super) fn new() -> Self {
         NodeMaker {
-            infos: Rc::new(RefCell::new(Vec::new())),
+            infos: Shared::new(Lock::new(Vec::new())),
         }
     }
-    pub(super) fn make_node<C: Op>(&self, deps: Vec<Weak<RefCell<NodeInfo>>>, inner: C) -> Node<C> {
+    pub(super) fn make_node<C: Op>(&self, deps: Vec<Weak<Lock<NodeInfo>>>, inner: C) -> Node<C> {
         let mut infos = self.infos.borrow_mut();
         let depth = deps
             .iter()
             .map(|x| x.upgrade().unwrap().borrow().depth)
             .max()
             .unwrap_or(0);
-        let info = Rc::new(RefCell::new(NodeInfo {
+        let info = Shared::new(Lock::new(NodeInfo {
             message_count: 0,
             name: None,
             shown: true,
@@ -109,13 +109,13 @@ impl NodeMaker {
             is_registrar: false,
             depth,
         }));
-        infos.push(Rc::clone(&info));
+        infos.push(Shared::clone(&info));
         Node { inner, info }
     }
 }
 
 impl<C: ?Sized> Relation<'_, C> {
-    pub(super) fn node_ref(&self) -> Weak<RefCell<NodeInfo>> {
-        Rc::downgrade(&self.inner.info)
+    pub(super) fn node_ref(&self) -> Weak<Lock<NodeInfo>> {
+        Shared::downgrade(&self.inner.info)
     }
 }
@@ -0,0 +1,317 @@
+use super::barrier::Barrier;
+use super::Op;
+use crate::core::borrow::BorrowOrDefault;
+use crate::core::hash::HashMap;
+use crate::core::is_map::{shrink, IsAddMap};
+use crate::core::iter::TupleableWith;
+use crate::core::key::Key;
+use crate::core::monoid::Monoid;
+use crate::core::node::Node;
+use crate::core::shared::{Lock, Shared, Weak};
+use crate::core::{compaction_pass, Relation, Step};
+use std::mem;
+use std::ops::Mul;
+
+type Listener<K, V, R> = Shared<Lock<HashMap<(K, V), R>>>;
+type WeakListener<K, V, R> = Weak<Lock<HashMap<(K, V), R>>>;
+
+struct Index<K, V, C: Op<D = (K, V)>> {
+    inner: Barrier<C>,
+    map: HashMap<K, HashMap<V, C::R>>,
+    // Weak so that readers which are dropped without ever being flowed (such as the one held by
+    // the `Arranged` itself) stop accumulating changes.
+    listeners: Vec<WeakListener<K, V, C::R>>,
+    /// The last compaction pass which reached this index.
+    compacted: usize,
+}
+
+struct IndexRef<K, V, C: Op<D = (K, V)>>(Shared<Lock<Index<K, V, C>>>);
+
+impl<K: Key, V: Key, C: Op<D = (K, V)>> IndexRef<K, V, C> {
+    fn propagate(&self, step: &Step) {
+        if self.0.borrow().inner.dirty(step) {
+            let mut index = self.0.borrow_mut();
+            let Index {
+                ref mut inner,
+                ref mut map,
+                ref mut listeners,
+                ..
+            } = &mut *index;
+            listeners.retain(|listener| listener.strong_count() > 0);
+            inner.flow(step, |x, r| {
+                map.add(x.clone(), r.clone());
+                for (listener, (x, r)) in listeners.iter().tuple_with((x, r)) {
+                    listener.upgrade().unwrap().borrow_mut().add(x, r);
+                }
+            });
+        }
+    }
+    fn compact(&self) -> usize {
+        let mut index = self.0.borrow_mut();
+        let pass = compaction_pass();
+        if index.compacted == pass {
+            return 0;
+        }
+        index.compacted = pass;
+        let Index {
+            ref mut inner,
+            ref mut map,
+            ref mut listeners,
+            ..
+        } = &mut *index;
+        listeners.retain(|listener| listener.strong_count() > 0);
+        let pending: usize = listeners
+            .iter()
+            .map(|listener| shrink(&mut listener.upgrade().unwrap().borrow_mut()))
+            .sum();
+        inner.compact() + map.compact() + pending
+    }
+    fn add_listener(&self, listener: &Listener<K, V, C::R>) {
+        self.0
+            .borrow_mut()
+            .listeners
+            .push(Shared::downgrade(listener))
+    }
+    /// Calls `f` with every value under `k` in the index, as of the last propagated step.
+    fn foreach<F: FnMut(&V, &C::R)>(&self, k: &K, mut f: F) {
+        for (v, r) in self.0.borrow().map.get(k).borrow_or_default().iter() {
+            f(v, r)
+        }
+    }
+}
+
+impl<K, V, C: Op<D = (K, V)>> Clone for IndexRef<K, V, C> {
+    fn clone(&self) -> Self {
+        IndexRef(Shared::clone(&self.0))
+    }
+}
+
+/// One consumer's view of a shared index: the changes it hasn't seen yet, plus access to the
+/// index itself.
+pub struct IndexReader<K, V, C: Op<D = (K, V)>> {
+    data: Listener<K, V, C::R>,
+    index: IndexRef<K, V, C>,
+}
+
+impl<K: Key, V: Key, C: Op<D = (K, V)>> Clone for IndexReader<K, V, C> {
+    fn clone(&self) -> Self {
+        let data = Shared::new(Lock::new(self.data.borrow().clone()));
+        self.index.add_listener(&data);
+        IndexReader {
+            data,
+            index: self.index.clone(),
+        }
+    }
+}
+
+impl<K: Key, V: Key, C: Op<D = (K, V)>> Op for IndexReader<K, V, C> {
+    type D = (K, V);
+    type R = C::R;
+
+    fn hideable() -> bool {
+        false
+    }
+    fn default_op_name() -> &'static str {
+        "arrange_by_key"
+    }
+    fn flow<F: FnMut((K, V), C::R)>(&mut self, step: &Step, mut send: F) {
+        self.index.propagate(step);
+        for (x, r) in mem::take(&mut *self.data.borrow_mut()) {
+            send(x, r)
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.index.compact()
+    }
+}
+
+/// An index of a relation by key which can be shared between several `join`, `semijoin` and
+/// `antijoin` operators. See `Relation::arrange_by_key`.
+pub struct Arranged<'a, K, V, C: Op<D = (K, V)>> {
+    reader: Relation<'a, IndexReader<K, V, C>>,
+}
+
+impl<'a, K: Key, V: Key, C: Op<D = (K, V)>> Clone for Arranged<'a, K, V, C> {
+    fn clone(&self) -> Self {
+        Arranged {
+            reader: self.reader.clone(),
+        }
+    }
+}
+
+impl<'a, K: Key, V: Key, C: Op<D = (K, V)>> Relation<'a, C> {
+    /// Builds an index of this relation by key which is maintained once per step, no matter how
+    /// many operators read from it. Joining the result against several relations avoids building
+    /// a separate copy of the index for each join.
+    pub fn arrange_by_key(self) -> Arranged<'a, K, V, C> {
+        let dep = self.dep();
+        let inner = Barrier::new(self.inner);
+        let data = Shared::new(Lock::new(HashMap::default()));
+        let index = IndexRef(Shared::new(Lock::new(Index {
+            inner,
+            map: HashMap::default(),
+            listeners: vec![Shared::downgrade(&data)],
+            compacted: 0,
+        })));
+        Arranged {
+            reader: Relation::new(vec![dep], IndexReader { data, index }),
+        }
+    }
+}
+
+impl<'a, K: Key, V: Key, C: Op<D = (K, V)>> Arranged<'a, K, V, C> {
+    pub fn named(self, name: &str) -> Self {
+        Arranged {
+            reader: self.reader.named(name),
+        }
+    }
+    /// The changes to the underlying relation, as an ordinary relation.
+    pub fn relation(&self) -> Relation<'a, impl Op<D = (K, V), R = C::R>> {
+        self.reader.clone()
+    }
+    pub fn join<C2: Op<D = (K, D2)>, D2: Key, OR: Monoid>(
+        &self,
+        other: Relation<'a, C2>,
+    ) -> Relation<'a, impl Op<D = (K, (V, D2)), R = OR>>
+    where
+        C::R: Mul<C2::R, Output = OR>,
+    {
+        let left = self.reader.clone();
+        Relation::new(
+            vec![left.dep(), other.dep()],
+            ArrangedJoin {
+                left: left.inner,
+                right: other.inner,
+                right_map: HashMap::default(),
+            },
+        )
+    }
+    pub fn semijoin<C2: Op<D = K, R = R2>, R2: Monoid, R3: Monoid>(
+        &self,
+        other: Relation<'a, C2>,
+    ) -> Relation<'a, impl Op<D = (K, V), R = R3>>
+    where
+        C::R: Mul<R2, Output = R3>,
+    {
+        self.join(other.hmap(|x| (x, ())))
+            .op_named("semijoin")
+            .hmap(|(k, (x, ()))| (k, x))
+    }
+    pub fn antijoin<C2: Op<D = K>>(
+        &self,
+        other: Relation<'a, C2>,
+    ) -> Relation<'a, impl Op<D = (K, V), R = C::R>> {
+        let left = self.reader.clone();
+        Relation::new(
+            vec![left.dep(), other.dep()],
+            ArrangedAntiJoin {
+                left: left.inner,
+                right: other.inner,
+                right_map: HashMap::default(),
+            },
+        )
+    }
+}
+
+struct ArrangedJoin<K, V, LC: Op<D = (K, V)>, RC, RD, RR> {
+    left: Node<IndexReader<K, V, LC>>,
+    right: Node<RC>,
+    right_map: HashMap<K, HashMap<RD, RR>>,
+}
+
+impl<
+        K: Key,
+        V: Key,
+        LC: Op<D = (K, V)>,
+        RC: Op<D = (K, RD), R = RR>,
+        RD: Key,
+        RR: Monoid,
+        OR: Monoid,
+    > Op for ArrangedJoin<K, V, LC, RC, RD, RR>
+where
+    LC::R: Mul<RR, Output = OR>,
+{
+    type D = (K, (V, RD));
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "join"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
+        let ArrangedJoin {
+            left,
+            right,
+            right_map,
+        } = self;
+        // The index already includes this step's changes on the left, so those are matched
+        // against the old right side, and the changes on the right against the new index.
+        left.flow(step, |(k, lx), lr| {
+            for (rx, rr) in right_map.get(&k).borrow_or_default().iter() {
+                send(
+                    (k.clone(), (lx.clone(), rx.clone())),
+                    lr.clone() * rr.clone(),
+                );
+            }
+        });
+        let mut right_changes = Vec::new();
+        right.flow(step, |x, r| right_changes.push((x, r)));
+        for ((k, rx), rr) in right_changes {
+            left.inner.index.foreach(&k, |lx, lr| {
+                send(
+                    (k.clone(), (lx.clone(), rx.clone())),
+                    lr.clone() * rr.clone(),
+                );
+            });
+            right_map.add((k, rx), rr);
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.left.compact() + self.right.compact() + self.right_map.compact()
+    }
+}
+
+struct ArrangedAntiJoin<K, V, LC: Op<D = (K, V)>, RC, RR> {
+    left: Node<IndexReader<K, V, LC>>,
+    right: Node<RC>,
+    right_map: HashMap<K, RR>,
+}
+
+impl<K: Key, V: Key, LC: Op<D = (K, V)>, RC: Op<D = K, R = RR>, RR: Monoid> Op
+    for ArrangedAntiJoin<K, V, LC, RC, RR>
+{
+    type D = (K, V);
+    type R = LC::R;
+
+    fn default_op_name() -> &'static str {
+        "antijoin"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
+        let ArrangedAntiJoin {
+            left,
+            right,
+            right_map,
+        } = self;
+        left.flow(step, |(k, lx), lr| {
+            if !right_map.contains_key(&k) {
+                send((k, lx), lr);
+            }
+        });
+        let mut right_changes = Vec::new();
+        right.flow(step, |k, r| right_changes.push((k, r)));
+        for (k, rr) in right_changes {
+            let was_nonzero = right_map.contains_key(&k);
+            right_map.add(k.clone(), rr);
+            let is_nonzero = right_map.contains_key(&k);
+            if is_nonzero != was_nonzero {
+                let negated = is_nonzero;
+                left.inner.index.foreach(&k, |lx, lr| {
+                    let nr = if negated { -lr.clone() } else { lr.clone() };
+                    send((k.clone(), lx.clone()), nr)
+                });
+            }
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.left.compact() + self.right.compact() + shrink(&mut self.right_map)
+    }
+}
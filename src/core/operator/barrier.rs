@@ -32,6 +32,9 @@ impl<C: Op> Op for Barrier<C> {
             self.inner.flow(step_for_depth, send);
         }
     }
+    fn compact(&mut self) -> usize {
+        self.inner.compact()
+    }
 }
 
 impl<'a, C: Op> Relation<'a, C> {
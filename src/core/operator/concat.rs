@@ -20,6 +20,9 @@ impl<D: Key, R: Monoid, C1: Op<D = D, R = R>, C2: Op<D = D, R = R>> Op for Conca
         self.left.flow(step, &mut send);
         self.right.flow(step, send);
     }
+    fn compact(&mut self) -> usize {
+        self.left.compact() + self.right.compact()
+    }
 }
 
 impl<'a, C: Op> Relation<'a, C> {
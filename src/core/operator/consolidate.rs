@@ -1,8 +1,8 @@
 use super::Op;
+use crate::core::hash::HashMap;
 use crate::core::is_map::IsAddMap;
 use crate::core::node::Node;
 use crate::core::{Relation, Step};
-use std::collections::HashMap;
 
 struct Consolidate<C> {
     inner: Node<C>,
@@ -16,12 +16,15 @@ impl<C: Op> Op for Consolidate<C> {
         "consolidate"
     }
     fn flow<F: FnMut(C::D, C::R)>(&mut self, step: &Step, mut send: F) {
-        let mut m = HashMap::new();
+        let mut m = HashMap::default();
         self.inner.flow(step, |x, r| m.add(x, r));
         for (x, r) in m {
             send(x, r)
         }
     }
+    fn compact(&mut self) -> usize {
+        self.inner.compact()
+    }
 }
 
 impl<'a, C: Op> Relation<'a, C> {
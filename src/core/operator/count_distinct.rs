@@ -0,0 +1,60 @@
+use super::Op;
+use crate::core::hash::HashMap;
+use crate::core::is_map::IsAddMap;
+use crate::core::key::Key;
+use crate::core::node::Node;
+use crate::core::{Relation, Step};
+
+struct CountDistinct<C: Op, K, V> {
+    inner: Node<C>,
+    // Entries are dropped as soon as their multiplicity reaches zero, so the length of each
+    // group's map is its distinct count.
+    values: HashMap<K, HashMap<V, C::R>>,
+}
+
+impl<C: Op<D = (K, V)>, K: Key, V: Key> Op for CountDistinct<C, K, V> {
+    type D = (K, usize);
+    type R = isize;
+
+    fn default_op_name() -> &'static str {
+        "group_count_distinct"
+    }
+    fn flow<F: FnMut((K, usize), isize)>(&mut self, step: &Step, mut send: F) {
+        let CountDistinct { inner, values } = self;
+        let mut changed = HashMap::default();
+        inner.flow(step, |(k, v), r| {
+            let count = values.get(&k).map_or(0, HashMap::len);
+            changed.entry(k.clone()).or_insert(count);
+            values.add((k, v), r);
+        });
+        for (k, old) in changed {
+            let new = values.get(&k).map_or(0, HashMap::len);
+            if new != old {
+                if old != 0 {
+                    send((k.clone(), old), -1);
+                }
+                if new != 0 {
+                    send((k, new), 1);
+                }
+            }
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.inner.compact() + self.values.compact()
+    }
+}
+
+impl<'a, K: Key, V: Key, C: Op<D = (K, V)>> Relation<'a, C> {
+    /// The number of distinct values with a nonzero multiplicity in each group, as `(key, count)`
+    /// records. Groups with no values have no record. A group's record only changes when one of its
+    /// values appears or disappears.
+    pub fn group_count_distinct(self) -> Relation<'a, impl Op<D = (K, usize), R = isize>> {
+        Relation::new(
+            vec![self.dep()],
+            CountDistinct {
+                inner: self.inner,
+                values: HashMap::default(),
+            },
+        )
+    }
+}
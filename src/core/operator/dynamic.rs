@@ -2,15 +2,17 @@ use super::Op;
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::Node;
+use crate::core::shared::MaybeSend;
 use crate::core::Relation;
 use crate::core::Step;
 
 pub struct DynOp<D, R = isize>(Box<dyn DynOpT<D = D, R = R>>);
 
-trait DynOpT: 'static {
+trait DynOpT: MaybeSend + 'static {
     type D: Key;
     type R: Monoid;
     fn flow_dyn(&mut self, step: &Step, send: &mut dyn FnMut(Self::D, Self::R));
+    fn compact_dyn(&mut self) -> usize;
 }
 
 impl<T: Op> DynOpT for Node<T> {
@@ -19,6 +21,9 @@ impl<T: Op> DynOpT for Node<T> {
     fn flow_dyn(&mut self, step: &Step, send: &mut dyn FnMut(Self::D, Self::R)) {
         self.flow(step, send)
     }
+    fn compact_dyn(&mut self) -> usize {
+        self.compact()
+    }
 }
 
 impl<D: Key, R: Monoid> Op for DynOp<D, R> {
@@ -30,6 +35,9 @@ impl<D: Key, R: Monoid> Op for DynOp<D, R> {
     fn flow<F: FnMut(D, R)>(&mut self, step: &Step, mut send: F) {
         self.0.flow_dyn(step, &mut send)
     }
+    fn compact(&mut self) -> usize {
+        self.0.compact_dyn()
+    }
 }
 
 impl<'a, C: Op> Relation<'a, C> {
@@ -1,47 +1,197 @@
 use super::Op;
-use crate::core::is_map::IsAddMap;
+use crate::core::hash;
+use crate::core::is_map::{shrink, IsAddMap};
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
+use crate::core::shared::{Lock, Ref, Shared};
 use crate::core::{ContextId, CreationContext, ExecutionContext, Relation, Step};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
 use std::marker::PhantomData;
 use std::mem;
-use std::rc::Rc;
 
 struct InputInner<D, R> {
     step: usize,
-    pending: HashMap<D, R>,
-    adding: HashMap<D, R>,
+    pending: hash::HashMap<D, R>,
+    adding: hash::HashMap<D, R>,
+    contents: Option<hash::HashMap<D, R>>,
+    checker: Option<Checker<D, R>>,
 }
 
+/// What a checked input (see `CreationContext::create_checked_input`) should do when an update
+/// would bring the net multiplicity of a record below zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputCheck {
+    /// Panic, reporting the offending record.
+    Panic,
+    /// Reject the update. `Input::try_update` and `Input::try_replace_all` return a
+    /// `NegativeMultiplicity` error (and `Input::update` and `Input::replace_all` panic).
+    Error,
+    /// Stage only as much of the update as brings the record's multiplicity to zero.
+    Clamp,
+}
+
+struct Checker<D, R> {
+    mode: InputCheck,
+    is_negative: fn(&R) -> bool,
+    describe: fn(&D, &R) -> String,
+}
+
+/// Returned by `Input::try_update` when an update to a checked input was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NegativeMultiplicity<D, R> {
+    pub record: D,
+    pub multiplicity: R,
+}
+
+impl<D: Debug, R: Debug> Display for NegativeMultiplicity<D, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} would have multiplicity {:?}",
+            self.record, self.multiplicity
+        )
+    }
+}
+
+impl<D: Debug, R: Debug> Error for NegativeMultiplicity<D, R> {}
+
 pub struct Input<D, R = isize> {
-    inner: Rc<RefCell<InputInner<D, R>>>,
+    inner: Shared<Lock<InputInner<D, R>>>,
     context_id: ContextId,
 }
-struct InputCollection<D, R>(Rc<RefCell<InputInner<D, R>>>);
+struct InputCollection<D, R>(Shared<Lock<InputInner<D, R>>>);
 
 impl<D: Key, R: Monoid> Input<D, R> {
     pub fn update(&self, context: &ExecutionContext, x: D, r: R) {
+        if let Err(err) = self.try_update(context, x, r) {
+            panic!(
+                "Negative multiplicity in checked input: {}",
+                self.inner.borrow().describe(&err)
+            )
+        }
+    }
+    /// Same as `update`, but if this is a checked input in `InputCheck::Error` mode and the
+    /// update would bring the record's net multiplicity (committed plus staged) below zero, the
+    /// update is rejected and reported rather than staged.
+    pub fn try_update(
+        &self,
+        context: &ExecutionContext,
+        x: D,
+        r: R,
+    ) -> Result<(), NegativeMultiplicity<D, R>> {
         assert_eq!(self.context_id, context.context_id);
         let mut inner_mut = self.inner.borrow_mut();
         inner_mut.resolve(context.step);
+        let (x, r) = inner_mut.check(x, r)?;
         inner_mut.adding.add(x, r);
+        Ok(())
+    }
+    /// Returns the consolidated contents of this input as of the last call to `commit`. Only
+    /// available on inputs created with `create_tracked_input`.
+    pub fn contents<'a>(&'a self, context: &'a ExecutionContext) -> Ref<'a, hash::HashMap<D, R>> {
+        assert_eq!(self.context_id, context.context_id);
+        self.inner.borrow_mut().resolve(context.step);
+        Ref::map(self.inner.borrow(), |i| {
+            i.contents
+                .as_ref()
+                .expect("Input does not track its contents")
+        })
+    }
+    /// Stages whatever changes are needed for the contents of this input to be exactly `xs` after
+    /// the next `commit`. Any changes already staged in this step are discarded. Only available on
+    /// inputs created with `create_tracked_input` (or `create_checked_input`, in which case each
+    /// change is checked just like an `update`).
+    pub fn replace_all<I: IntoIterator<Item = (D, R)>>(&self, context: &ExecutionContext, xs: I) {
+        if let Err(err) = self.try_replace_all(context, xs) {
+            panic!(
+                "Negative multiplicity in checked input: {}",
+                self.inner.borrow().describe(&err)
+            )
+        }
+    }
+    /// Same as `replace_all`, but if this is a checked input in `InputCheck::Error` mode and `xs`
+    /// gives a record a negative multiplicity, nothing is staged and the record is reported. The
+    /// changes staged before the call are then kept.
+    pub fn try_replace_all<I: IntoIterator<Item = (D, R)>>(
+        &self,
+        context: &ExecutionContext,
+        xs: I,
+    ) -> Result<(), NegativeMultiplicity<D, R>> {
+        assert_eq!(self.context_id, context.context_id);
+        let mut inner_mut = self.inner.borrow_mut();
+        inner_mut.resolve(context.step);
+        let contents = inner_mut
+            .contents
+            .as_ref()
+            .expect("Input does not track its contents");
+        let mut changes = hash::HashMap::default();
+        for (x, r) in xs {
+            changes.add(x, r);
+        }
+        for (x, r) in contents.iter() {
+            changes.add(x.clone(), -r.clone());
+        }
+        let staged = mem::take(&mut inner_mut.adding);
+        for (x, r) in changes {
+            match inner_mut.check(x, r) {
+                Ok((x, r)) => inner_mut.adding.add(x, r),
+                Err(err) => {
+                    inner_mut.adding = staged;
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
     }
 }
 impl<D, R> Clone for Input<D, R> {
     fn clone(&self) -> Self {
         Input {
-            inner: Rc::clone(&self.inner),
+            inner: Shared::clone(&self.inner),
             context_id: self.context_id,
         }
     }
 }
 impl<D: Key, R: Monoid> InputInner<D, R> {
+    fn describe(&self, err: &NegativeMultiplicity<D, R>) -> String {
+        let checker = self.checker.as_ref().unwrap();
+        (checker.describe)(&err.record, &err.multiplicity)
+    }
+    fn check(&self, x: D, r: R) -> Result<(D, R), NegativeMultiplicity<D, R>> {
+        let checker = match self.checker.as_ref() {
+            None => return Ok((x, r)),
+            Some(checker) => checker,
+        };
+        let mut net = r.clone();
+        if let Some(cr) = self.contents.as_ref().unwrap().get(&x) {
+            net += cr.clone();
+        }
+        if let Some(ar) = self.adding.get(&x) {
+            net += ar.clone();
+        }
+        if !(checker.is_negative)(&net) {
+            return Ok((x, r));
+        }
+        match checker.mode {
+            InputCheck::Panic => panic!(
+                "Negative multiplicity in checked input: {}",
+                (checker.describe)(&x, &net)
+            ),
+            InputCheck::Error => Err(NegativeMultiplicity {
+                record: x,
+                multiplicity: net,
+            }),
+            InputCheck::Clamp => Ok((x, r - net)),
+        }
+    }
     fn resolve(&mut self, step: usize) {
         assert!(self.step <= step);
         if self.step < step {
             for (x, r) in mem::take(&mut self.adding) {
+                if let Some(contents) = self.contents.as_mut() {
+                    contents.add(x.clone(), r.clone());
+                }
                 self.pending.add(x, r);
             }
             self.step = step;
@@ -68,20 +218,62 @@ impl<D: Key, R: Monoid> Op for InputCollection<D, R> {
     fn flow<F: FnMut(D, R)>(&mut self, step: &Step, send: F) {
         self.0.borrow_mut().flow(step, send)
     }
+    fn compact(&mut self) -> usize {
+        let mut inner = self.0.borrow_mut();
+        let contents = inner.contents.as_mut().map_or(0, shrink);
+        contents + shrink(&mut inner.pending) + shrink(&mut inner.adding)
+    }
 }
 
 impl CreationContext {
     pub fn create_input<D: Key, R: Monoid>(
         &self,
     ) -> (Input<D, R>, Relation<'static, impl Op<D = D, R = R>>) {
-        let inner = Rc::new(RefCell::new(InputInner {
+        self.make_input(None, None)
+    }
+    /// Like `create_input`, but the returned `Input` additionally maintains its consolidated
+    /// committed contents so they can be inspected with `Input::contents` or overwritten with
+    /// `Input::replace_all`.
+    pub fn create_tracked_input<D: Key, R: Monoid>(
+        &self,
+    ) -> (Input<D, R>, Relation<'static, impl Op<D = D, R = R>>) {
+        self.make_input(Some(hash::HashMap::default()), None)
+    }
+    /// Like `create_tracked_input`, but every update is validated against the committed and
+    /// staged contents of the input so that no record's net multiplicity ever goes below zero.
+    /// `mode` determines what happens to an update which would violate this.
+    pub fn create_checked_input<D: Key + Debug, R: Monoid + PartialOrd + Debug>(
+        &self,
+        mode: InputCheck,
+    ) -> (Input<D, R>, Relation<'static, impl Op<D = D, R = R>>) {
+        let checker = Checker {
+            mode,
+            is_negative: |r: &R| *r < R::default(),
+            describe: |x: &D, r: &R| {
+                NegativeMultiplicity {
+                    record: x,
+                    multiplicity: r,
+                }
+                .to_string()
+            },
+        };
+        self.make_input(Some(hash::HashMap::default()), Some(checker))
+    }
+    fn make_input<D: Key, R: Monoid>(
+        &self,
+        contents: Option<hash::HashMap<D, R>>,
+        checker: Option<Checker<D, R>>,
+    ) -> (Input<D, R>, Relation<'static, InputCollection<D, R>>) {
+        let inner = Shared::new(Lock::new(InputInner {
             step: 0,
-            pending: HashMap::new(),
-            adding: HashMap::new(),
+            pending: hash::HashMap::default(),
+            adding: hash::HashMap::default(),
+            contents,
+            checker,
         }));
         (
             Input {
-                inner: Rc::clone(&inner),
+                inner: Shared::clone(&inner),
                 context_id: self.context_id,
             },
             Relation {
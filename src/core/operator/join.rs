@@ -1,63 +1,138 @@
 use super::Op;
 use crate::core::borrow::BorrowOrDefault;
-use crate::core::is_map::IsAddMap;
+use crate::core::hash::HashMap;
+use crate::core::is_map::{shrink, IsAddMap};
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::Node;
+use crate::core::shared::MaybeSend;
 use crate::core::{Relation, Step};
-use std::collections::HashMap;
 use std::ops::Mul;
 
-struct Join<LC, RC, K, LD, LR, RD, RR> {
+/// Both sides of a join, each indexed by a key separated from its records. Shared by `join` and
+/// `join_on`, which differ only in how records are split and how matches are combined.
+struct Indexed<LC: Op, RC: Op, K, LV, RV> {
     left: Node<LC>,
     right: Node<RC>,
-    left_map: HashMap<K, HashMap<LD, LR>>,
-    right_map: HashMap<K, HashMap<RD, RR>>,
+    left_map: HashMap<K, HashMap<LV, LC::R>>,
+    right_map: HashMap<K, HashMap<RV, RC::R>>,
 }
 
-impl<
-        LC: Op<D = (K, LD), R = LR>,
-        RC: Op<D = (K, RD), R = RR>,
-        K: Key,
-        LD: Key,
-        LR: Monoid + Mul<RR, Output = OR>,
-        RD: Key,
-        RR: Monoid,
-        OR: Monoid,
-    > Op for Join<LC, RC, K, LD, LR, RD, RR>
-{
-    type D = (K, (LD, RD));
-    type R = OR;
-
-    fn default_op_name() -> &'static str {
-        "join"
+impl<LC: Op, RC: Op, K: Key, LV: Key, RV: Key> Indexed<LC, RC, K, LV, RV> {
+    fn new(left: Node<LC>, right: Node<RC>) -> Self {
+        Indexed {
+            left,
+            right,
+            left_map: HashMap::default(),
+            right_map: HashMap::default(),
+        }
     }
-    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
-        let Join {
+    fn flow<D, OR>(
+        &mut self,
+        step: &Step,
+        left_split: impl Fn(LC::D) -> (K, LV),
+        right_split: impl Fn(RC::D) -> (K, RV),
+        combine: impl Fn(&K, &LV, &RV) -> D,
+        mut send: impl FnMut(D, OR),
+    ) where
+        LC::R: Mul<RC::R, Output = OR>,
+    {
+        let Indexed {
             left,
             right,
             left_map,
             right_map,
         } = self;
-        left.flow(step, |(k, lx), lr| {
+        left.flow(step, |lx, lr| {
+            let (k, lx) = left_split(lx);
             for (rx, rr) in right_map.get(&k).borrow_or_default().iter() {
-                send(
-                    (k.clone(), (lx.clone(), rx.clone())),
-                    lr.clone() * rr.clone(),
-                );
+                send(combine(&k, &lx, rx), lr.clone() * rr.clone());
             }
             left_map.add((k, lx), lr);
         });
-        right.flow(step, |(k, rx), rr| {
+        right.flow(step, |rx, rr| {
+            let (k, rx) = right_split(rx);
             for (lx, lr) in left_map.get(&k).borrow_or_default().iter() {
-                send(
-                    (k.clone(), (lx.clone(), rx.clone())),
-                    lr.clone() * rr.clone(),
-                );
+                send(combine(&k, lx, &rx), lr.clone() * rr.clone());
             }
             right_map.add((k, rx), rr);
         });
     }
+    fn compact(&mut self) -> usize {
+        self.left.compact()
+            + self.right.compact()
+            + self.left_map.compact()
+            + self.right_map.compact()
+    }
+}
+
+struct Join<LC: Op, RC: Op, K, LD, RD>(Indexed<LC, RC, K, LD, RD>);
+
+impl<LC: Op<D = (K, LD)>, RC: Op<D = (K, RD)>, K: Key, LD: Key, RD: Key, OR: Monoid> Op
+    for Join<LC, RC, K, LD, RD>
+where
+    LC::R: Mul<RC::R, Output = OR>,
+{
+    type D = (K, (LD, RD));
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "join"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, send: F) {
+        self.0.flow(
+            step,
+            |lx| lx,
+            |rx| rx,
+            |k, lx, rx| (k.clone(), (lx.clone(), rx.clone())),
+            send,
+        )
+    }
+    fn compact(&mut self) -> usize {
+        self.0.compact()
+    }
+}
+
+struct JoinOn<LC: Op, RC: Op, K, LF, RF> {
+    inner: Indexed<LC, RC, K, LC::D, RC::D>,
+    left_key: LF,
+    right_key: RF,
+}
+
+impl<
+        LC: Op,
+        RC: Op,
+        K: Key,
+        LF: Fn(&LC::D) -> K + MaybeSend + 'static,
+        RF: Fn(&RC::D) -> K + MaybeSend + 'static,
+        OR: Monoid,
+    > Op for JoinOn<LC, RC, K, LF, RF>
+where
+    LC::R: Mul<RC::R, Output = OR>,
+{
+    type D = (LC::D, RC::D);
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "join_on"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, send: F) {
+        let JoinOn {
+            inner,
+            left_key,
+            right_key,
+        } = self;
+        inner.flow(
+            step,
+            |lx| (left_key(&lx), lx),
+            |rx| (right_key(&rx), rx),
+            |_, lx, rx| (lx.clone(), rx.clone()),
+            send,
+        )
+    }
+    fn compact(&mut self) -> usize {
+        self.inner.compact()
+    }
 }
 
 struct AntiJoin<LC, RC, K, LD, LR, RR> {
@@ -108,6 +183,12 @@ impl<
             left_map.add((k, lx), lr);
         });
     }
+    fn compact(&mut self) -> usize {
+        self.left.compact()
+            + self.right.compact()
+            + self.left_map.compact()
+            + shrink(&mut self.right_map)
+    }
 }
 
 impl<'a, K: Key, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
@@ -120,12 +201,7 @@ impl<'a, K: Key, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
     {
         Relation::new(
             vec![self.dep(), other.dep()],
-            Join {
-                left: self.inner,
-                right: other.inner,
-                left_map: HashMap::new(),
-                right_map: HashMap::new(),
-            },
+            Join(Indexed::new(self.inner, other.inner)),
         )
     }
     pub fn antijoin<C2: Op<D = K>>(
@@ -137,8 +213,38 @@ impl<'a, K: Key, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
             AntiJoin {
                 left: self.inner,
                 right: other.inner,
-                left_map: HashMap::new(),
-                right_map: HashMap::new(),
+                left_map: HashMap::default(),
+                right_map: HashMap::default(),
+            },
+        )
+    }
+}
+
+impl<'a, C: Op> Relation<'a, C> {
+    /// Joins whole records of this relation and `other` whose keys (as computed by `left_key` and
+    /// `right_key`) are equal. Equivalent to mapping each side to `(key, record)` pairs, joining
+    /// and dropping the key, but without the intermediate operators.
+    pub fn join_on<
+        C2: Op,
+        K: Key,
+        LF: Fn(&C::D) -> K + MaybeSend + 'static,
+        RF: Fn(&C2::D) -> K + MaybeSend + 'static,
+        OR: Monoid,
+    >(
+        self,
+        other: Relation<'a, C2>,
+        left_key: LF,
+        right_key: RF,
+    ) -> Relation<'a, impl Op<D = (C::D, C2::D), R = OR>>
+    where
+        C::R: Mul<C2::R, Output = OR>,
+    {
+        Relation::new(
+            vec![self.dep(), other.dep()],
+            JoinOn {
+                inner: Indexed::new(self.inner, other.inner),
+                left_key,
+                right_key,
             },
         )
     }
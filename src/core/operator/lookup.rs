@@ -0,0 +1,100 @@
+use super::Op;
+use crate::core::hash;
+use crate::core::is_map::{shrink, IsAddMap};
+use crate::core::key::Key;
+use crate::core::monoid::Monoid;
+use crate::core::node::Node;
+use crate::core::shared::{MaybeSync, Shared};
+use crate::core::{Relation, Step};
+use std::iter::FromIterator;
+use std::ops::Mul;
+
+/// An immutable index of reference data, built once up front and shared (cheaply, by cloning)
+/// between any number of `join_static` operators.
+pub struct StaticTable<K, V, R = isize>(Shared<hash::HashMap<K, hash::HashMap<V, R>>>);
+
+impl<K, V, R> Clone for StaticTable<K, V, R> {
+    fn clone(&self) -> Self {
+        StaticTable(Shared::clone(&self.0))
+    }
+}
+
+impl<K: Key, V: Key, R: Monoid> StaticTable<K, V, R> {
+    pub fn from_weighted<I: IntoIterator<Item = ((K, V), R)>>(iter: I) -> Self {
+        let mut map = hash::HashMap::default();
+        for (x, r) in iter {
+            map.add(x, r);
+        }
+        for values in map.values_mut() {
+            shrink(values);
+        }
+        shrink(&mut map);
+        StaticTable(Shared::new(map))
+    }
+    pub fn get(&self, k: &K) -> Option<&hash::HashMap<V, R>> {
+        self.0.get(k)
+    }
+}
+
+impl<K: Key, V: Key> FromIterator<(K, V)> for StaticTable<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from_weighted(iter.into_iter().map(|x| (x, 1)))
+    }
+}
+
+struct StaticJoin<C, K, V, R> {
+    inner: Node<C>,
+    table: StaticTable<K, V, R>,
+}
+
+impl<
+        C: Op<D = (K, D)>,
+        K: Key + MaybeSync,
+        D: Key,
+        V: Key + MaybeSync,
+        R: Monoid + MaybeSync,
+        OR: Monoid,
+    > Op for StaticJoin<C, K, V, R>
+where
+    C::R: Mul<R, Output = OR>,
+{
+    type D = (K, (D, V));
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "join_static"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
+        let StaticJoin { inner, table } = self;
+        inner.flow(step, |(k, x), r| {
+            if let Some(values) = table.get(&k) {
+                for (v, tr) in values.iter() {
+                    send((k.clone(), (x.clone(), v.clone())), r.clone() * tr.clone());
+                }
+            }
+        });
+    }
+    fn compact(&mut self) -> usize {
+        self.inner.compact()
+    }
+}
+
+impl<'a, K: Key + MaybeSync, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
+    /// Joins this relation against `table`. Unlike `join`, nothing about this relation is
+    /// indexed: each change is looked up in the table as it arrives.
+    pub fn join_static<V: Key + MaybeSync, R: Monoid + MaybeSync, OR: Monoid>(
+        self,
+        table: &StaticTable<K, V, R>,
+    ) -> Relation<'a, impl Op<D = (K, (D, V)), R = OR>>
+    where
+        C::R: Mul<R, Output = OR>,
+    {
+        Relation::new(
+            vec![self.dep()],
+            StaticJoin {
+                inner: self.inner,
+                table: table.clone(),
+            },
+        )
+    }
+}
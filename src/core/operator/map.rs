@@ -2,6 +2,7 @@ use super::Op;
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::Node;
+use crate::core::shared::MaybeSend;
 use crate::core::{Relation, Step};
 
 struct FlatMap<C, MF> {
@@ -16,7 +17,7 @@ impl<
         R2: Monoid,
         C: Op<D = D1, R = R1>,
         I: IntoIterator<Item = (D2, R2)>,
-        MF: Fn(D1, R1) -> I + 'static,
+        MF: Fn(D1, R1) -> I + MaybeSend + 'static,
     > Op for FlatMap<C, MF>
 {
     type D = D2;
@@ -36,11 +37,14 @@ impl<
             }
         })
     }
+    fn compact(&mut self) -> usize {
+        self.inner.compact()
+    }
 }
 
 impl<'a, C: Op> Relation<'a, C> {
     pub fn flat_map_dr<
-        F: Fn(C::D, C::R) -> I + 'static,
+        F: Fn(C::D, C::R) -> I + MaybeSend + 'static,
         D2: Key,
         R2: Monoid,
         I: IntoIterator<Item = (D2, R2)>,
@@ -1,24 +1,34 @@
+mod arrange;
 mod barrier;
 mod concat;
 mod consolidate;
+mod count_distinct;
 mod dynamic;
 mod input;
 mod join;
+mod lookup;
 mod map;
+mod product;
+mod range_join;
+mod rank;
 mod reduce;
 mod split;
 pub mod subgraph;
+mod threshold;
 mod triangles;
 
+pub use self::arrange::Arranged;
 pub use self::dynamic::DynOp;
-pub use self::input::Input;
+pub use self::input::{Input, InputCheck, NegativeMultiplicity};
+pub use self::lookup::StaticTable;
 pub use self::reduce::{IsReduce, ReduceOutput};
 pub use self::split::Receiver;
 use super::Step;
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
+use crate::core::shared::MaybeSend;
 
-pub trait Op: 'static {
+pub trait Op: MaybeSend + 'static {
     type D: Key;
     type R: Monoid;
     fn hideable() -> bool {
@@ -26,4 +36,7 @@ pub trait Op: 'static {
     }
     fn default_op_name() -> &'static str;
     fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, send: F);
+    /// Releases spare capacity held by this operator and everything upstream of it, returning the
+    /// number of entries' worth which was released.
+    fn compact(&mut self) -> usize;
 }
@@ -0,0 +1,106 @@
+use super::Op;
+use crate::core::hash::HashMap;
+use crate::core::is_map::IsAddMap;
+use crate::core::key::Key;
+use crate::core::monoid::Monoid;
+use crate::core::node::Node;
+use crate::core::{Relation, Step};
+use std::ops::Mul;
+
+struct Product<LC: Op, RC: Op> {
+    left: Node<LC>,
+    right: Node<RC>,
+    left_map: HashMap<LC::D, LC::R>,
+    right_map: HashMap<RC::D, RC::R>,
+}
+
+/// Sends every pair from `left` and `right`.
+fn cross<LD: Key, LR: Monoid + Mul<RR, Output = OR>, RD: Key, RR: Monoid, OR>(
+    left: &HashMap<LD, LR>,
+    right: &HashMap<RD, RR>,
+    send: &mut impl FnMut((LD, RD), OR),
+) {
+    for (lx, lr) in left {
+        for (rx, rr) in right {
+            send((lx.clone(), rx.clone()), lr.clone() * rr.clone());
+        }
+    }
+}
+
+impl<LC: Op, RC: Op, OR: Monoid> Op for Product<LC, RC>
+where
+    LC::R: Mul<RC::R, Output = OR>,
+{
+    type D = (LC::D, RC::D);
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "product"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
+        let Product {
+            left,
+            right,
+            left_map,
+            right_map,
+        } = self;
+        // (S + dS) * (L + dL) - S * L = dS * L + dL * (S + dS), where S is whichever side is
+        // currently smaller. Only its changes are collected; the larger side's changes are sent
+        // as they arrive, without building a map of them first.
+        if left_map.len() <= right_map.len() {
+            let mut left_changes = HashMap::default();
+            left.flow(step, |x, r| left_changes.add(x, r));
+            cross(&left_changes, right_map, &mut send);
+            for (x, r) in left_changes {
+                left_map.add(x, r);
+            }
+            right.flow(step, |rx, rr| {
+                for (lx, lr) in left_map.iter() {
+                    send((lx.clone(), rx.clone()), lr.clone() * rr.clone());
+                }
+                right_map.add(rx, rr);
+            });
+        } else {
+            let mut right_changes = HashMap::default();
+            right.flow(step, |x, r| right_changes.add(x, r));
+            cross(left_map, &right_changes, &mut send);
+            for (x, r) in right_changes {
+                right_map.add(x, r);
+            }
+            left.flow(step, |lx, lr| {
+                for (rx, rr) in right_map.iter() {
+                    send((lx.clone(), rx.clone()), lr.clone() * rr.clone());
+                }
+                left_map.add(lx, lr);
+            });
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.left.compact()
+            + self.right.compact()
+            + self.left_map.compact()
+            + self.right_map.compact()
+    }
+}
+
+impl<'a, C: Op> Relation<'a, C> {
+    /// The cartesian product of this relation and `other`. Both sides are kept as plain multisets
+    /// rather than being indexed by a key, so prefer `join` whenever there is one.
+    pub fn product<C2: Op, OR: Monoid>(
+        self,
+        other: Relation<'a, C2>,
+    ) -> Relation<'a, impl Op<D = (C::D, C2::D), R = OR>>
+    where
+        C::R: Mul<C2::R, Output = OR>,
+    {
+        Relation::new(
+            vec![self.dep(), other.dep()],
+            Product {
+                left: self.inner,
+                right: other.inner,
+                left_map: HashMap::default(),
+                right_map: HashMap::default(),
+            },
+        )
+    }
+}
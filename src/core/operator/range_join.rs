@@ -0,0 +1,206 @@
+use super::Op;
+use crate::core::hash::HashMap;
+use crate::core::is_map::IsAddMap;
+use crate::core::key::{Key, SaturatingKey};
+use crate::core::monoid::Monoid;
+use crate::core::node::Node;
+use crate::core::{Relation, Step};
+use std::collections::BTreeMap;
+use std::ops::Mul;
+
+struct BandJoin<LC, RC, K, LD, LR, RD, RR> {
+    left: Node<LC>,
+    right: Node<RC>,
+    left_map: BTreeMap<K, HashMap<LD, LR>>,
+    right_map: BTreeMap<K, HashMap<RD, RR>>,
+    below: K,
+    above: K,
+}
+
+impl<
+        LC: Op<D = (K, LD), R = LR>,
+        RC: Op<D = (K, RD), R = RR>,
+        K: SaturatingKey,
+        LD: Key,
+        LR: Monoid + Mul<RR, Output = OR>,
+        RD: Key,
+        RR: Monoid,
+        OR: Monoid,
+    > Op for BandJoin<LC, RC, K, LD, LR, RD, RR>
+{
+    type D = ((K, LD), (K, RD));
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "band_join"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
+        let BandJoin {
+            left,
+            right,
+            left_map,
+            right_map,
+            below,
+            above,
+        } = self;
+        left.flow(step, |(lk, lx), lr| {
+            let lo = lk.clone().saturating_sub(below.clone());
+            let hi = lk.clone().saturating_add(above.clone());
+            if lo <= hi {
+                for (rk, rxs) in right_map.range(lo..=hi) {
+                    for (rx, rr) in rxs.iter() {
+                        send(
+                            ((lk.clone(), lx.clone()), (rk.clone(), rx.clone())),
+                            lr.clone() * rr.clone(),
+                        );
+                    }
+                }
+            }
+            left_map.add((lk, lx), lr);
+        });
+        right.flow(step, |(rk, rx), rr| {
+            let lo = rk.clone().saturating_sub(above.clone());
+            let hi = rk.clone().saturating_add(below.clone());
+            if lo <= hi {
+                for (lk, lxs) in left_map.range(lo..=hi) {
+                    for (lx, lr) in lxs.iter() {
+                        send(
+                            ((lk.clone(), lx.clone()), (rk.clone(), rx.clone())),
+                            lr.clone() * rr.clone(),
+                        );
+                    }
+                }
+            }
+            right_map.add((rk, rx), rr);
+        });
+    }
+    fn compact(&mut self) -> usize {
+        self.left.compact()
+            + self.right.compact()
+            + self.left_map.compact()
+            + self.right_map.compact()
+    }
+}
+
+struct IntervalJoin<LC, RC, K, LD, LR, RD, RR> {
+    left: Node<LC>,
+    right: Node<RC>,
+    left_map: BTreeMap<K, HashMap<LD, LR>>,
+    // Intervals indexed by their start.
+    right_map: BTreeMap<K, HashMap<(K, RD), RR>>,
+}
+
+impl<
+        LC: Op<D = (K, LD), R = LR>,
+        RC: Op<D = ((K, K), RD), R = RR>,
+        K: Key + Ord,
+        LD: Key,
+        LR: Monoid + Mul<RR, Output = OR>,
+        RD: Key,
+        RR: Monoid,
+        OR: Monoid,
+    > Op for IntervalJoin<LC, RC, K, LD, LR, RD, RR>
+{
+    type D = ((K, LD), ((K, K), RD));
+    type R = OR;
+
+    fn default_op_name() -> &'static str {
+        "interval_join"
+    }
+    fn flow<F: FnMut(Self::D, Self::R)>(&mut self, step: &Step, mut send: F) {
+        let IntervalJoin {
+            left,
+            right,
+            left_map,
+            right_map,
+        } = self;
+        left.flow(step, |(t, lx), lr| {
+            for (start, rxs) in right_map.range(..=t.clone()) {
+                for ((end, rx), rr) in rxs.iter() {
+                    if *end > t {
+                        send(
+                            (
+                                (t.clone(), lx.clone()),
+                                ((start.clone(), end.clone()), rx.clone()),
+                            ),
+                            lr.clone() * rr.clone(),
+                        );
+                    }
+                }
+            }
+            left_map.add((t, lx), lr);
+        });
+        right.flow(step, |((start, end), rx), rr| {
+            if start < end {
+                for (t, lxs) in left_map.range(start.clone()..end.clone()) {
+                    for (lx, lr) in lxs.iter() {
+                        send(
+                            (
+                                (t.clone(), lx.clone()),
+                                ((start.clone(), end.clone()), rx.clone()),
+                            ),
+                            lr.clone() * rr.clone(),
+                        );
+                    }
+                }
+            }
+            right_map.add((start, (end, rx)), rr);
+        });
+    }
+    fn compact(&mut self) -> usize {
+        self.left.compact()
+            + self.right.compact()
+            + self.left_map.compact()
+            + self.right_map.compact()
+    }
+}
+
+impl<'a, K: Key + Ord, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
+    /// Joins each record `(k, x)` of this relation with every record `(k2, y)` of `other` where
+    /// `k - below <= k2 <= k + above`, with the bounds clamped to the range of `K`.
+    #[allow(clippy::type_complexity)]
+    pub fn band_join<C2: Op<D = (K, D2)>, D2: Key, OR: Monoid>(
+        self,
+        other: Relation<'a, C2>,
+        below: K,
+        above: K,
+    ) -> Relation<'a, impl Op<D = ((K, D), (K, D2)), R = OR>>
+    where
+        K: SaturatingKey,
+        C::R: Mul<C2::R, Output = OR>,
+    {
+        Relation::new(
+            vec![self.dep(), other.dep()],
+            BandJoin {
+                left: self.inner,
+                right: other.inner,
+                left_map: BTreeMap::new(),
+                right_map: BTreeMap::new(),
+                below,
+                above,
+            },
+        )
+    }
+    /// Joins each record `(t, x)` of this relation with every record `((start, end), y)` of
+    /// `other` where `start <= t < end`. Finding the intervals containing a new `t` scans every
+    /// interval starting at or before it, so this suits intervals that mostly lie ahead of the
+    /// events being joined to them.
+    #[allow(clippy::type_complexity)]
+    pub fn interval_join<C2: Op<D = ((K, K), D2)>, D2: Key, OR: Monoid>(
+        self,
+        other: Relation<'a, C2>,
+    ) -> Relation<'a, impl Op<D = ((K, D), ((K, K), D2)), R = OR>>
+    where
+        C::R: Mul<C2::R, Output = OR>,
+    {
+        Relation::new(
+            vec![self.dep(), other.dep()],
+            IntervalJoin {
+                left: self.inner,
+                right: other.inner,
+                left_map: BTreeMap::new(),
+                right_map: BTreeMap::new(),
+            },
+        )
+    }
+}
@@ -0,0 +1,114 @@
+use super::Op;
+use crate::core::hash::HashMap;
+use crate::core::is_map::{shrink, IsAddMap};
+use crate::core::key::Key;
+use crate::core::node::Node;
+use crate::core::{Relation, Step};
+use std::collections::BTreeMap;
+
+/// The values in one group, in order, along with the total number of records among them. A
+/// value's rank is then the total less the number of records at or after it, so finding the
+/// ranks from some value onwards only walks the values from there onwards. Those are exactly the
+/// values whose ranks a change to that value can affect.
+struct Group<V> {
+    values: BTreeMap<V, isize>,
+    total: isize,
+}
+
+impl<V: Ord + Clone> Group<V> {
+    fn add(&mut self, v: V, r: isize) {
+        let old = self.values.get(&v).copied().unwrap_or(0);
+        self.total += (old + r).max(0) - old.max(0);
+        self.values.add(v, r);
+    }
+    /// Sends each record from `from` onwards with its rank (or with its row number, one record
+    /// at a time, if `row_numbers` is set). Values with non-positive counts are skipped.
+    fn ranks<F: FnMut((V, usize), isize)>(&self, from: &V, row_numbers: bool, mut send: F) {
+        let after: isize = self.values.range(from..).map(|(_, &c)| c.max(0)).sum();
+        let mut position = (self.total - after + 1) as usize;
+        for (v, &c) in self.values.range(from..) {
+            if c <= 0 {
+                continue;
+            }
+            if row_numbers {
+                for i in 0..c as usize {
+                    send((v.clone(), position + i), 1);
+                }
+            } else {
+                send((v.clone(), position), c);
+            }
+            position += c as usize;
+        }
+    }
+}
+
+struct Rank<C, K, V> {
+    inner: Node<C>,
+    groups: HashMap<K, Group<V>>,
+    row_numbers: bool,
+}
+
+impl<C: Op<D = (K, V), R = isize>, K: Key, V: Key + Ord> Op for Rank<C, K, V> {
+    type D = (K, (V, usize));
+    type R = isize;
+
+    fn default_op_name() -> &'static str {
+        "group_rank"
+    }
+    fn flow<F: FnMut(Self::D, isize)>(&mut self, step: &Step, mut send: F) {
+        let Rank {
+            inner,
+            groups,
+            row_numbers,
+        } = self;
+        let mut changes: HashMap<K, HashMap<V, isize>> = HashMap::default();
+        inner.flow(step, |x, r| changes.add(x, r));
+        for (k, vs) in changes {
+            let from = vs.keys().min().unwrap().clone();
+            let group = groups.entry(k.clone()).or_insert_with(|| Group {
+                values: BTreeMap::new(),
+                total: 0,
+            });
+            let mut diff = HashMap::default();
+            group.ranks(&from, *row_numbers, |x, r| diff.add(x, -r));
+            for (v, r) in vs {
+                group.add(v, r);
+            }
+            group.ranks(&from, *row_numbers, |x, r| diff.add(x, r));
+            if group.values.is_empty() {
+                groups.remove(&k);
+            }
+            for (x, r) in diff {
+                send((k.clone(), x), r);
+            }
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.inner.compact() + shrink(&mut self.groups)
+    }
+}
+
+impl<'a, K: Key, V: Key + Ord, C: Op<D = (K, V), R = isize>> Relation<'a, C> {
+    fn rank(self, row_numbers: bool) -> Relation<'a, Rank<C, K, V>> {
+        Relation::new(
+            vec![self.dep()],
+            Rank {
+                inner: self.inner,
+                groups: HashMap::default(),
+                row_numbers,
+            },
+        )
+    }
+    /// Pairs each value with its rank within its key's group: one more than the number of records
+    /// in the group with smaller values, so equal values share a rank. Use `std::cmp::Reverse` to
+    /// rank from largest to smallest. Only the records whose rank changed are updated, and values
+    /// with non-positive multiplicities are ignored.
+    pub fn group_rank(self) -> Relation<'a, impl Op<D = (K, (V, usize)), R = isize>> {
+        self.rank(false)
+    }
+    /// Like `group_rank`, but numbers every record in the group consecutively from 1, so a value
+    /// with multiplicity `n` is paired with `n` different row numbers.
+    pub fn group_row_number(self) -> Relation<'a, impl Op<D = (K, (V, usize)), R = isize>> {
+        self.rank(true).op_named("group_row_number")
+    }
+}
@@ -1,20 +1,21 @@
 use super::barrier::Barrier;
 use super::split::{Receiver, SourceRef};
 use super::Op;
-use crate::core::is_map::{IsAddMap, IsDiscardMap, IsMap, IsRemoveMap};
+use crate::core::hash::{self, HashSet};
+use crate::core::is_map::{shrink, IsAddMap, IsDiscardMap, IsMap, IsRemoveMap};
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::Node;
+use crate::core::shared::{Lock, MaybeSend, Ref, Shared};
 use crate::core::{ContextId, CreationContext, ExecutionContext, Relation, Step};
-use std::cell::{Ref, RefCell};
-use std::collections::{hash_map, HashMap, HashSet};
+use std::collections::{hash_map, HashMap};
 use std::marker::PhantomData;
 use std::mem;
 use std::ops::Deref;
 
 pub struct Reduce<D2, R2, C, K, M1, M2, F: Fn(&K, &M1) -> M2> {
     inner: Node<C>,
-    input_maps: HashMap<K, M1>,
+    input_maps: hash::HashMap<K, M1>,
     output_maps: HashMap<K, M2>,
     proc: F,
     phantom: PhantomData<(D2, R2)>,
@@ -24,9 +25,9 @@ impl<
         C: Op<D = (K, D1)>,
         K: Key,
         D1,
-        M1: IsAddMap<D1, C::R> + 'static,
-        M2: IsMap<D2, R2> + 'static,
-        MF: Fn(&K, &M1) -> M2 + 'static,
+        M1: IsAddMap<D1, C::R> + MaybeSend + 'static,
+        M2: IsMap<D2, R2> + MaybeSend + 'static,
+        MF: Fn(&K, &M1) -> M2 + MaybeSend + 'static,
         D2: Key,
         R2: Monoid,
     > Op for Reduce<D2, R2, C, K, M1, M2, MF>
@@ -38,7 +39,7 @@ impl<
         "reduce"
     }
     fn flow<F: FnMut((K, D2), R2)>(&mut self, step: &Step, mut send: F) {
-        let mut changed_keys = HashSet::new();
+        let mut changed_keys = HashSet::default();
         let Reduce {
             inner, input_maps, ..
         } = self;
@@ -86,15 +87,18 @@ impl<
             }
         }
     }
+    fn compact(&mut self) -> usize {
+        self.inner.compact() + self.input_maps.compact() + shrink(&mut self.output_maps)
+    }
 }
 
 impl<'a, K: Key, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
     pub fn reduce<
         D2: Key,
         R2: Monoid,
-        MF: Fn(&K, &M1) -> M2 + 'static,
-        M1: IsAddMap<D, C::R> + 'static,
-        M2: IsMap<D2, R2> + 'static,
+        MF: Fn(&K, &M1) -> M2 + MaybeSend + 'static,
+        M1: IsAddMap<D, C::R> + MaybeSend + 'static,
+        M2: IsMap<D2, R2> + MaybeSend + 'static,
     >(
         self,
         proc: MF,
@@ -103,8 +107,8 @@ impl<'a, K: Key, D: Key, C: Op<D = (K, D)>> Relation<'a, C> {
             vec![self.dep()],
             Reduce {
                 inner: self.inner,
-                input_maps: HashMap::new(),
-                output_maps: HashMap::new(),
+                input_maps: HashMap::default(),
+                output_maps: HashMap::default(),
                 proc,
                 phantom: PhantomData,
             },
@@ -124,14 +128,14 @@ impl<
         D2: Key,
         R2: Monoid,
         C: Op<D = (K, D1)>,
-        F: Fn(&K, &M1) -> M2 + 'static,
-        M1: IsAddMap<D1, C::R> + 'static,
-        M2: IsMap<D2, R2> + 'static,
+        F: Fn(&K, &M1) -> M2 + MaybeSend + 'static,
+        M1: IsAddMap<D1, C::R> + MaybeSend + 'static,
+        M2: IsMap<D2, R2> + MaybeSend + 'static,
     > IsReduce for Reduce<D2, R2, C, K, M1, M2, F>
 {
     type K = K;
     type M = M2;
-    fn get_ref<'a>(&'a self) -> &'a HashMap<K, M2> {
+    fn get_ref(&self) -> &HashMap<K, M2> {
         &self.output_maps
     }
 }
@@ -149,17 +153,20 @@ impl<C: IsReduce + Op> Relation<'static, C> {
         let context_id = self.context_id;
         let r = self.split();
         let inner = r.inner.inner.get_source_ref();
+        context.add_compactor(inner.compactor());
         (r, SplitReduceOutputImpl { context_id, inner })
     }
     pub fn reduce_output(self, context: &CreationContext) -> impl ReduceOutput<K = C::K, M = C::M> {
         assert_eq!(self.context_id, context.context_id, "Context mismatch");
         assert_eq!(self.depth(), 0);
         let context_id = self.context_id;
-        let r = self.barrier();
-        ReduceOutputImpl {
-            context_id,
-            inner: RefCell::new(r.inner),
-        }
+        let inner = Shared::new(Lock::new(self.barrier().inner));
+        let weak = Shared::downgrade(&inner);
+        context.add_compactor(Box::new(move || {
+            weak.upgrade()
+                .map_or(0, |inner| inner.borrow_mut().compact())
+        }));
+        ReduceOutputImpl { context_id, inner }
     }
 }
 
@@ -170,7 +177,7 @@ pub struct SplitReduceOutputImpl<C: Op> {
 
 pub struct ReduceOutputImpl<C: Op> {
     context_id: ContextId,
-    inner: RefCell<Node<Barrier<C>>>,
+    inner: Shared<Lock<Node<Barrier<C>>>>,
 }
 
 impl<C: IsReduce + Op> ReduceOutput for SplitReduceOutputImpl<C> {
@@ -1,26 +1,38 @@
 use super::barrier::Barrier;
 use super::Op;
-use crate::core::is_map::IsAddMap;
+use crate::core::hash::HashMap;
+use crate::core::is_map::{shrink, IsAddMap};
 use crate::core::iter::TupleableWith;
 use crate::core::node::Node;
-use crate::core::{Relation, Step};
-use std::cell::{Ref, RefCell, RefMut};
-use std::collections::HashMap;
+use crate::core::shared::{Lock, MaybeSend, Ref, RefMut, Shared};
+use crate::core::{compaction_pass, Compactor, Relation, Step};
 use std::mem;
-use std::rc::Rc;
+
+type Listener<C> = Shared<Lock<HashMap<<C as Op>::D, <C as Op>::R>>>;
+
+/// Picks the partition a record is sent to.
+#[cfg(not(feature = "sync"))]
+type Route<D> = Box<dyn Fn(&D) -> usize>;
+#[cfg(feature = "sync")]
+type Route<D> = Box<dyn Fn(&D) -> usize + Send>;
 
 struct Source<C: Op> {
     inner: Barrier<C>,
-    listeners: Vec<Rc<RefCell<HashMap<C::D, C::R>>>>,
+    /// Each listener along with the partition it receives. Without a route, every listener
+    /// receives every record.
+    listeners: Vec<(usize, Listener<C>)>,
+    route: Option<Route<C::D>>,
+    /// The last compaction pass which reached this source.
+    compacted: usize,
 }
 
-pub(super) struct SourceRef<C: Op>(Rc<RefCell<Source<C>>>);
+pub(super) struct SourceRef<C: Op>(Shared<Lock<Source<C>>>);
 
 impl<C: Op> SourceRef<C> {
-    pub(super) fn get_inner(&self) -> Ref<Node<C>> {
+    pub(super) fn get_inner(&self) -> Ref<'_, Node<C>> {
         Ref::map(self.0.borrow(), |r| &r.inner.inner)
     }
-    pub(super) fn get_inner_mut(&self) -> RefMut<Node<C>> {
+    pub(super) fn get_inner_mut(&self) -> RefMut<'_, Node<C>> {
         RefMut::map(self.0.borrow_mut(), |r| &mut r.inner.inner)
     }
     pub(super) fn propagate(&self, step: &Step) {
@@ -29,38 +41,98 @@ impl<C: Op> SourceRef<C> {
             let Source {
                 ref mut inner,
                 ref listeners,
+                ref route,
+                ..
             } = &mut *source;
-            inner.flow(step, |x, r| {
-                for (listener, (x, r)) in listeners.iter().tuple_with((x, r)) {
-                    listener.borrow_mut().add(x, r);
+            inner.flow(step, |x, r| match route {
+                None => {
+                    for ((_, listener), (x, r)) in listeners.iter().tuple_with((x, r)) {
+                        listener.borrow_mut().add(x, r);
+                    }
+                }
+                Some(route) => {
+                    let partition = route(&x);
+                    let matching = listeners.iter().filter(|(p, _)| *p == partition);
+                    for ((_, listener), (x, r)) in matching.tuple_with((x, r)) {
+                        listener.borrow_mut().add(x, r);
+                    }
                 }
             });
         }
     }
-    fn add_listener(&self, listener: Rc<RefCell<HashMap<C::D, C::R>>>) {
-        self.0.borrow_mut().listeners.push(listener)
+    /// Compacts everything upstream of this source as well as the changes pending for each of its
+    /// receivers, unless the current compaction pass already did so through another receiver.
+    pub(super) fn compact(&self) -> usize {
+        let mut source = self.0.borrow_mut();
+        let pass = compaction_pass();
+        if source.compacted == pass {
+            return 0;
+        }
+        source.compacted = pass;
+        let pending: usize = source
+            .listeners
+            .iter()
+            .map(|(_, listener)| shrink(&mut listener.borrow_mut()))
+            .sum();
+        pending + source.inner.compact()
+    }
+    pub(super) fn compactor(&self) -> Compactor {
+        let weak = Shared::downgrade(&self.0);
+        Box::new(move || {
+            weak.upgrade()
+                .map_or(0, |source| SourceRef(source).compact())
+        })
+    }
+    fn add_listener(&self, partition: usize, listener: Listener<C>) {
+        self.0.borrow_mut().listeners.push((partition, listener))
     }
 }
 
 pub struct Receiver<C: Op> {
-    data: Rc<RefCell<HashMap<C::D, C::R>>>,
+    data: Listener<C>,
     source: SourceRef<C>,
+    partition: usize,
 }
 
 impl<C: Op> Receiver<C> {
     pub(super) fn new(from: Node<C>) -> Self {
         let inner = Barrier::new(from);
-        let data = Rc::new(RefCell::new(HashMap::new()));
-        let source = SourceRef(Rc::new(RefCell::new(Source {
+        let data = Shared::new(Lock::new(HashMap::default()));
+        let source = SourceRef(Shared::new(Lock::new(Source {
             inner,
-            listeners: vec![Rc::clone(&data)],
+            listeners: vec![(0, Shared::clone(&data))],
+            route: None,
+            compacted: 0,
+        })));
+        Receiver {
+            data,
+            source,
+            partition: 0,
+        }
+    }
+    fn partitioned(from: Node<C>, n: usize, route: Route<C::D>) -> Vec<Self> {
+        let data: Vec<Listener<C>> = (0..n)
+            .map(|_| Shared::new(Lock::new(HashMap::default())))
+            .collect();
+        let source = SourceRef(Shared::new(Lock::new(Source {
+            inner: Barrier::new(from),
+            listeners: data.iter().cloned().enumerate().collect(),
+            route: Some(route),
+            compacted: 0,
         })));
-        Receiver { data, source }
+        data.into_iter()
+            .enumerate()
+            .map(|(partition, data)| Receiver {
+                data,
+                source: source.clone(),
+                partition,
+            })
+            .collect()
     }
-    pub(super) fn get_inner(&self) -> Ref<Node<C>> {
+    pub(super) fn get_inner(&self) -> Ref<'_, Node<C>> {
         self.source.get_inner()
     }
-    pub(super) fn get_inner_mut(&self) -> RefMut<Node<C>> {
+    pub(super) fn get_inner_mut(&self) -> RefMut<'_, Node<C>> {
         self.source.get_inner_mut()
     }
     pub(super) fn get_source_ref(&self) -> SourceRef<C> {
@@ -70,17 +142,19 @@ impl<C: Op> Receiver<C> {
 
 impl<C: Op> Clone for SourceRef<C> {
     fn clone(&self) -> Self {
-        SourceRef(Rc::clone(&self.0))
+        SourceRef(Shared::clone(&self.0))
     }
 }
 
 impl<C: Op> Clone for Receiver<C> {
     fn clone(&self) -> Self {
-        let data = Rc::new(RefCell::new(self.data.borrow().clone()));
-        self.source.add_listener(Rc::clone(&data));
+        let data = Shared::new(Lock::new(self.data.borrow().clone()));
+        self.source
+            .add_listener(self.partition, Shared::clone(&data));
         Receiver {
             data,
             source: self.source.clone(),
+            partition: self.partition,
         }
     }
 }
@@ -98,6 +172,9 @@ impl<C: Op> Op for Receiver<C> {
             send(x, r)
         }
     }
+    fn compact(&mut self) -> usize {
+        self.source.compact()
+    }
 }
 
 impl<'a, C: Op> Relation<'a, C> {
@@ -105,4 +182,34 @@ impl<'a, C: Op> Relation<'a, C> {
     pub fn split(self) -> Relation<'a, Receiver<C>> {
         Relation::new(vec![self.dep()], Receiver::new(self.inner)).hidden()
     }
+    /// Splits this relation into `n` relations, sending each record to the one numbered `f(record)`
+    /// (which must be less than `n`). `f` is called once per change, however many of the outputs
+    /// are used. Panics if `n` is zero.
+    pub fn partition<F: Fn(&C::D) -> usize + MaybeSend + 'static>(
+        self,
+        n: usize,
+        f: F,
+    ) -> Vec<Relation<'a, Receiver<C>>> {
+        assert!(n > 0, "Partitioning into no outputs");
+        let deps: Vec<_> = (0..n).map(|_| self.dep()).collect();
+        let route = Box::new(move |x: &C::D| {
+            let partition = f(x);
+            assert!(partition < n, "Partition {} out of range", partition);
+            partition
+        });
+        deps.into_iter()
+            .zip(Receiver::partitioned(self.inner, n, route))
+            .map(|(dep, receiver)| Relation::new(vec![dep], receiver).hidden())
+            .collect()
+    }
+    /// Splits this relation into the records satisfying `pred` and the rest.
+    pub fn partition_by<F: Fn(&C::D) -> bool + MaybeSend + 'static>(
+        self,
+        pred: F,
+    ) -> (Relation<'a, Receiver<C>>, Relation<'a, Receiver<C>>) {
+        let mut parts = self
+            .partition(2, move |x| if pred(x) { 0 } else { 1 })
+            .into_iter();
+        (parts.next().unwrap(), parts.next().unwrap())
+    }
 }
@@ -22,6 +22,9 @@ impl<S: Key + Ord, C: Op> Op for Leave<S, C> {
         self.inner
             .flow(&step.append(self.registrar.get_inner_step()), send)
     }
+    fn compact(&mut self) -> usize {
+        self.inner.compact() + self.registrar.compact()
+    }
 }
 
 impl<'b, C: Op> Relation<'b, C> {
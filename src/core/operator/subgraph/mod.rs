@@ -9,10 +9,9 @@ use self::contextual::IsContext;
 use self::registrar::Registrar;
 pub use self::variable::Variable;
 use crate::core::node::{NodeInfo, NodeMaker};
+use crate::core::shared::{Lock, Ref, Shared};
 use crate::core::{key::Key, Dep};
 use crate::core::{ContextId, CreationContext};
-use std::cell::{Ref, RefCell};
-use std::rc::Rc;
 
 impl<'a, Ctx: IsContext, S: Key + Ord> IsContext for SubContext<'a, Ctx, S> {
     fn get_context_id(&self) -> ContextId {
@@ -38,7 +37,7 @@ pub struct Finalizer<'a, Ctx, S: Key + Ord> {
 }
 
 impl<Ctx: IsContext, S: Key + Ord> Finalizer<'_, Ctx, S> {
-    fn node_ref(&self) -> Ref<Rc<RefCell<NodeInfo>>> {
+    fn node_ref(&self) -> Ref<'_, Shared<Lock<NodeInfo>>> {
         Ref::map(self.registrar.get_inner(), |x| &x.info)
     }
     fn dep(&self) -> Dep {
@@ -46,7 +45,7 @@ impl<Ctx: IsContext, S: Key + Ord> Finalizer<'_, Ctx, S> {
         node_info.borrow_mut().depth = Ctx::get_depth();
         Dep {
             context_id: self.parent.get_context_id(),
-            node_info: Rc::downgrade(&node_info),
+            node_info: Shared::downgrade(&node_info),
             node_maker: self.node_maker.clone(),
         }
     }
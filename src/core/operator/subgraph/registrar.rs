@@ -2,8 +2,8 @@ use super::stepper::{IsStepper, Stepper};
 use crate::core::key::Key;
 use crate::core::node::NodeMaker;
 use crate::core::operator::{Op, Receiver};
+use crate::core::shared::Shared;
 use crate::core::Step;
-use std::rc::Rc;
 
 pub(super) struct RegistrarInner<S> {
     steppers: Vec<Box<dyn IsStepper<S>>>,
@@ -43,6 +43,12 @@ impl<S: Key + Ord> Op for RegistrarInner<S> {
             }
         }
     }
+    fn compact(&mut self) -> usize {
+        self.steppers
+            .iter_mut()
+            .map(|stepper| stepper.compact())
+            .sum()
+    }
 }
 
 impl<S: Key + Ord> Registrar<S> {
@@ -70,7 +76,7 @@ impl<S: Key + Ord> Registrar<S> {
             .info
             .borrow_mut()
             .deps
-            .push(Rc::downgrade(stepper.node_ref()));
+            .push(Shared::downgrade(stepper.node_ref()));
         inner.inner.steppers.push(Box::new(stepper));
     }
     pub(super) fn get_inner_step(&self) -> usize {
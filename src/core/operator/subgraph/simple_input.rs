@@ -1,14 +1,14 @@
+use crate::core::hash::HashMap;
+use crate::core::is_map::shrink;
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::operator::Op;
+use crate::core::shared::{Lock, Shared};
 use crate::core::Step;
-use std::cell::RefCell;
-use std::collections::HashMap;
 use std::mem;
-use std::rc::Rc;
 
 pub(super) struct SimpleInput<D, R> {
-    pub(super) pending: Rc<RefCell<HashMap<D, R>>>,
+    pub(super) pending: Shared<Lock<HashMap<D, R>>>,
 }
 
 impl<D: Key, R: Monoid> Op for SimpleInput<D, R> {
@@ -25,4 +25,7 @@ impl<D: Key, R: Monoid> Op for SimpleInput<D, R> {
             send(x, r)
         }
     }
+    fn compact(&mut self) -> usize {
+        shrink(&mut self.pending.borrow_mut())
+    }
 }
@@ -1,29 +1,30 @@
-use crate::core::is_map::IsAddMap;
+use crate::core::hash::HashMap;
+use crate::core::is_map::{shrink, IsAddMap};
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::{Node, NodeInfo};
 use crate::core::operator::Op;
+use crate::core::shared::{Lock, MaybeSend, Shared};
 use crate::core::Step;
-use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
-use std::rc::Rc;
+use std::collections::BTreeMap;
 
-pub(super) trait IsStepper<S> {
+pub(super) trait IsStepper<S>: MaybeSend {
     fn flow(&mut self, step: &Step);
     fn min_key(&self) -> Option<&S>;
     fn propagate(&mut self, key: &S);
+    fn compact(&mut self) -> usize;
 }
 
 pub(super) struct Stepper<S, D, R, C: Op<D = (S, D), R = R>> {
     pending: BTreeMap<S, HashMap<D, R>>,
-    input: Rc<RefCell<HashMap<(S, D), R>>>,
+    input: Shared<Lock<HashMap<(S, D), R>>>,
     output: Node<C>,
 }
 
 impl<S, D, R, C: Op<D = (S, D), R = R>> Stepper<S, D, R, C> {
     pub(super) fn new(
         pending: BTreeMap<S, HashMap<D, R>>,
-        input: Rc<RefCell<HashMap<(S, D), R>>>,
+        input: Shared<Lock<HashMap<(S, D), R>>>,
         output: Node<C>,
     ) -> Self {
         Stepper {
@@ -32,7 +33,7 @@ impl<S, D, R, C: Op<D = (S, D), R = R>> Stepper<S, D, R, C> {
             output,
         }
     }
-    pub(super) fn node_ref(&self) -> &Rc<RefCell<NodeInfo>> {
+    pub(super) fn node_ref(&self) -> &Shared<Lock<NodeInfo>> {
         &self.output.info
     }
 }
@@ -59,4 +60,8 @@ impl<S: Key + Ord, D: Key, R: Monoid, C: Op<D = (S, D), R = R>> IsStepper<S>
             }
         }
     }
+    fn compact(&mut self) -> usize {
+        let pending: usize = self.pending.values_mut().map(shrink).sum();
+        pending + shrink(&mut self.input.borrow_mut()) + self.output.compact()
+    }
 }
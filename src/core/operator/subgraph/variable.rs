@@ -2,22 +2,22 @@ use super::contextual::IsContext;
 use super::simple_input::SimpleInput;
 use super::stepper::Stepper;
 use super::{Registrar, SubContext};
+use crate::core::hash::HashMap;
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::NodeInfo;
 use crate::core::operator::Op;
+use crate::core::shared::{Lock, Shared};
 use crate::core::{ContextId, Relation};
-use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
-use std::rc::Rc;
 
 pub struct Variable<'a, S: Key + Ord, D, R> {
-    inner: Rc<RefCell<HashMap<(S, D), R>>>,
+    inner: Shared<Lock<HashMap<(S, D), R>>>,
     context_id: ContextId,
     registrar: Registrar<S>,
     phantom: PhantomData<&'a ()>,
-    info: Rc<RefCell<NodeInfo>>,
+    info: Shared<Lock<NodeInfo>>,
 }
 
 impl<'a, S: Key + Ord, D: Key, R: Monoid> Variable<'a, S, D, R> {
@@ -36,14 +36,14 @@ impl<'a, Ctx: IsContext, S: Key + Ord> SubContext<'a, Ctx, S> {
         Variable<'a, S, D, R>,
         Relation<'a, impl Op<D = (S, D), R = R>>,
     ) {
-        let pending = Rc::new(RefCell::new(HashMap::new()));
+        let pending = Shared::new(Lock::new(HashMap::default()));
         let depth = Self::get_depth();
         let new_node = self
             .node_maker
             .make_node(
-                vec![Rc::downgrade(&self.registrar.get_inner().info)],
+                vec![Shared::downgrade(&self.registrar.get_inner().info)],
                 SimpleInput {
-                    pending: Rc::clone(&pending),
+                    pending: Shared::clone(&pending),
                 },
             )
             .with_depth(depth);
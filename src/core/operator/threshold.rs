@@ -0,0 +1,58 @@
+use super::Op;
+use crate::core::hash::HashMap;
+use crate::core::is_map::IsAddMap;
+use crate::core::monoid::Monoid;
+use crate::core::node::Node;
+use crate::core::shared::MaybeSend;
+use crate::core::{Relation, Step};
+
+struct Threshold<C: Op, F> {
+    inner: Node<C>,
+    totals: HashMap<C::D, C::R>,
+    f: F,
+}
+
+impl<C: Op, R2: Monoid, F: Fn(&C::R) -> R2 + MaybeSend + 'static> Op for Threshold<C, F> {
+    type D = C::D;
+    type R = R2;
+
+    fn default_op_name() -> &'static str {
+        "threshold"
+    }
+    fn flow<S: FnMut(C::D, R2)>(&mut self, step: &Step, mut send: S) {
+        let Threshold { inner, totals, f } = self;
+        let mut changes = HashMap::default();
+        inner.flow(step, |x, r| changes.add(x, r));
+        for (x, r) in changes {
+            let old = totals.get(&x).cloned().unwrap_or_default();
+            let mut new = old.clone();
+            new += r.clone();
+            let diff = f(&new) - f(&old);
+            totals.add(x.clone(), r);
+            if !diff.is_zero() {
+                send(x, diff);
+            }
+        }
+    }
+    fn compact(&mut self) -> usize {
+        self.inner.compact() + self.totals.compact()
+    }
+}
+
+impl<'a, C: Op> Relation<'a, C> {
+    /// Maps the accumulated multiplicity of each record through `f`, which must send zero to zero.
+    /// `distinct` is the special case where `f` sends every positive multiplicity to one.
+    pub fn threshold<R2: Monoid, F: Fn(&C::R) -> R2 + MaybeSend + 'static>(
+        self,
+        f: F,
+    ) -> Relation<'a, impl Op<D = C::D, R = R2>> {
+        Relation::new(
+            vec![self.dep()],
+            Threshold {
+                inner: self.inner,
+                totals: HashMap::default(),
+                f,
+            },
+        )
+    }
+}
@@ -1,11 +1,11 @@
 use super::Op;
 use crate::core::borrow::BorrowOrDefault;
+use crate::core::hash::HashMap;
 use crate::core::is_map::IsAddMap;
 use crate::core::key::Key;
 use crate::core::monoid::Monoid;
 use crate::core::node::Node;
 use crate::core::{Relation, Step};
-use std::collections::HashMap;
 use std::hash::Hash;
 use std::ops::Mul;
 
@@ -18,8 +18,8 @@ struct BiMap<A, B, C: Op<D = (A, B)>> {
 impl<A, B, C: Op<D = (A, B)>> BiMap<A, B, C> {
     fn new(inner: Node<C>) -> Self {
         BiMap {
-            forward: HashMap::new(),
-            backward: HashMap::new(),
+            forward: HashMap::default(),
+            backward: HashMap::default(),
             inner,
         }
     }
@@ -41,6 +41,9 @@ where
             send((x, y), r)
         })
     }
+    fn compact(&mut self) -> usize {
+        self.inner.compact() + self.forward.compact() + self.backward.compact()
+    }
 }
 
 struct Triangles<
@@ -109,6 +112,9 @@ impl<
             }
         });
     }
+    fn compact(&mut self) -> usize {
+        self.mxy.compact() + self.mxz.compact() + self.myz.compact()
+    }
 }
 
 fn intersection<K: Clone + Eq + Hash, V1: Clone, V2: Clone>(
@@ -0,0 +1,102 @@
+//! Flushing several outputs at once on a pool of threads. Outputs whose upstream graphs share no
+//! nodes are independent, so they can be brought up to date concurrently; outputs which do share
+//! nodes are flushed one after another on the same thread, in the order they were given.
+
+use super::arrangement::Arrangement;
+use super::is_map::IsAddMap;
+use super::node::NodeInfo;
+use super::operator::Op;
+use super::shared::{Lock, Shared};
+use super::ExecutionContext;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::thread;
+
+/// The set of nodes an output reads from, directly or indirectly.
+pub struct Upstream(HashSet<usize>);
+
+impl Upstream {
+    pub(super) fn of(info: &Shared<Lock<NodeInfo>>) -> Self {
+        let mut seen = HashSet::new();
+        let mut stack = vec![Shared::clone(info)];
+        while let Some(info_ref) = stack.pop() {
+            let info = info_ref.borrow();
+            if seen.insert(info.relation_id) {
+                stack.extend(info.deps.iter().map(|dep| dep.upgrade().unwrap()));
+            }
+        }
+        Upstream(seen)
+    }
+}
+
+/// Something which can be brought up to date with the current step without being read.
+pub trait Flush: Sync {
+    fn flush(&self, context: &ExecutionContext);
+    fn upstream(&self) -> Upstream;
+}
+
+impl<C: Op, M: IsAddMap<C::D, C::R> + Send> Flush for Arrangement<C::D, C::R, M, C> {
+    fn flush(&self, context: &ExecutionContext) {
+        drop(self.read(context));
+    }
+    fn upstream(&self) -> Upstream {
+        Upstream::of(&self.node_info())
+    }
+}
+
+impl ExecutionContext {
+    /// Brings every one of `outputs` up to date with the current step, using up to `threads`
+    /// threads. The result is the same as reading each of them in turn.
+    pub fn flush_parallel(&self, outputs: &[&dyn Flush], threads: usize) {
+        let groups = independent_groups(outputs);
+        let threads = threads.max(1).min(groups.len());
+        let queue = Mutex::new(groups.into_iter());
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let group = queue.lock().unwrap().next();
+                    match group {
+                        None => break,
+                        Some(group) => {
+                            for output in group {
+                                output.flush(self);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Partitions `outputs` into groups such that no two groups share an upstream node. Each group
+/// keeps the original relative order of its outputs.
+fn independent_groups<'a, 'b>(outputs: &'b [&'a dyn Flush]) -> Vec<Vec<&'a dyn Flush>> {
+    let mut parent: Vec<usize> = (0..outputs.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut owner = HashMap::new();
+    for (i, output) in outputs.iter().enumerate() {
+        for node in output.upstream().0 {
+            let j = *owner.entry(node).or_insert(i);
+            let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+            parent[ri.max(rj)] = ri.min(rj);
+        }
+    }
+    let mut groups: Vec<Vec<&'a dyn Flush>> = Vec::new();
+    let mut group_of = HashMap::new();
+    for (i, output) in outputs.iter().enumerate() {
+        let r = root(&mut parent, i);
+        let g = *group_of.entry(r).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(*output);
+    }
+    groups
+}
@@ -0,0 +1,152 @@
+//! Semirings for weighted relations. Semiring weights generally can't be negated (there's no
+//! inverse for `min`), so rather than being used as multiplicities they're carried in the records
+//! themselves: a weighted relation is a relation of `(record, weight)` pairs with ordinary `isize`
+//! multiplicities counting how many times each weight was derived. Retracting a derivation is then
+//! an ordinary retraction, and `semiring_sum` recomputes the total weight of each record from
+//! whichever derivations remain. See `join_weighted` and `semiring_sum`.
+
+use crate::core::key::Key;
+use std::ops::Add;
+
+pub trait Semiring: Key {
+    /// The identity of `plus`, and the weight of a record which isn't present at all.
+    fn zero() -> Self;
+    /// The identity of `times`.
+    fn one() -> Self;
+    fn plus(&self, other: &Self) -> Self;
+    fn times(&self, other: &Self) -> Self;
+    /// `self` plussed with itself `n` times (`zero` if `n` is 0).
+    fn plus_n(&self, n: usize) -> Self {
+        (0..n).fold(Self::zero(), |acc, _| acc.plus(self))
+    }
+}
+
+/// The counting semiring.
+impl Semiring for isize {
+    fn zero() -> Self {
+        0
+    }
+    fn one() -> Self {
+        1
+    }
+    fn plus(&self, other: &Self) -> Self {
+        self + other
+    }
+    fn times(&self, other: &Self) -> Self {
+        self * other
+    }
+    fn plus_n(&self, n: usize) -> Self {
+        self * n as isize
+    }
+}
+
+/// The boolean semiring: `plus` is "or" and `times` is "and".
+impl Semiring for bool {
+    fn zero() -> Self {
+        false
+    }
+    fn one() -> Self {
+        true
+    }
+    fn plus(&self, other: &Self) -> Self {
+        *self || *other
+    }
+    fn times(&self, other: &Self) -> Self {
+        *self && *other
+    }
+    fn plus_n(&self, n: usize) -> Self {
+        n > 0 && *self
+    }
+}
+
+/// The tropical semiring: `plus` is `min` and `times` is `+`, so summing over the products along
+/// each path gives the length of the shortest one. Zero is infinity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MinPlus<T>(Option<T>);
+
+impl<T> MinPlus<T> {
+    pub fn new(t: T) -> Self {
+        MinPlus(Some(t))
+    }
+    pub fn infinity() -> Self {
+        MinPlus(None)
+    }
+    /// `None` for infinity.
+    pub fn get(&self) -> Option<&T> {
+        self.0.as_ref()
+    }
+}
+
+impl<T: Key + Ord + Default + Add<Output = T>> Semiring for MinPlus<T> {
+    fn zero() -> Self {
+        MinPlus(None)
+    }
+    fn one() -> Self {
+        MinPlus(Some(T::default()))
+    }
+    fn plus(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(l), Some(r)) => MinPlus(Some(l.clone().min(r.clone()))),
+            (l, r) => MinPlus(l.as_ref().or(r.as_ref()).cloned()),
+        }
+    }
+    fn times(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(l), Some(r)) => MinPlus(Some(l.clone() + r.clone())),
+            _ => MinPlus(None),
+        }
+    }
+    fn plus_n(&self, n: usize) -> Self {
+        if n == 0 {
+            Self::zero()
+        } else {
+            self.clone()
+        }
+    }
+}
+
+/// Like `MinPlus` but with `max` in place of `min`, giving the length of the longest path. Zero
+/// is negative infinity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaxPlus<T>(Option<T>);
+
+impl<T> MaxPlus<T> {
+    pub fn new(t: T) -> Self {
+        MaxPlus(Some(t))
+    }
+    pub fn negative_infinity() -> Self {
+        MaxPlus(None)
+    }
+    /// `None` for negative infinity.
+    pub fn get(&self) -> Option<&T> {
+        self.0.as_ref()
+    }
+}
+
+impl<T: Key + Ord + Default + Add<Output = T>> Semiring for MaxPlus<T> {
+    fn zero() -> Self {
+        MaxPlus(None)
+    }
+    fn one() -> Self {
+        MaxPlus(Some(T::default()))
+    }
+    fn plus(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(l), Some(r)) => MaxPlus(Some(l.clone().max(r.clone()))),
+            (l, r) => MaxPlus(l.as_ref().or(r.as_ref()).cloned()),
+        }
+    }
+    fn times(&self, other: &Self) -> Self {
+        match (&self.0, &other.0) {
+            (Some(l), Some(r)) => MaxPlus(Some(l.clone() + r.clone())),
+            _ => MaxPlus(None),
+        }
+    }
+    fn plus_n(&self, n: usize) -> Self {
+        if n == 0 {
+            Self::zero()
+        } else {
+            self.clone()
+        }
+    }
+}
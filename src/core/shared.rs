@@ -0,0 +1,181 @@
+//! Shared mutable state used to wire operators together. By default this is plain
+//! `Rc<RefCell<..>>`. With the `sync` feature it becomes `Arc<Lock<..>>` (a thread-safe
+//! `RefCell`) so that contexts, inputs and arrangements can be moved to other threads.
+
+#[cfg(not(feature = "sync"))]
+pub use std::cell::{Ref, RefCell as Lock, RefMut};
+#[cfg(not(feature = "sync"))]
+pub use std::rc::{Rc as Shared, Weak};
+
+#[cfg(feature = "sync")]
+pub use self::sync::{Lock, Ref, RefMut};
+#[cfg(feature = "sync")]
+pub use std::sync::{Arc as Shared, Weak};
+
+/// Implemented for every type when the `sync` feature is disabled, and only for `Send` types when
+/// it's enabled.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// Implemented for every type when the `sync` feature is disabled, and only for `Send` types when
+/// it's enabled.
+#[cfg(feature = "sync")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "sync")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// Implemented for every type when the `sync` feature is disabled, and only for `Sync` types when
+/// it's enabled.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
+
+/// Implemented for every type when the `sync` feature is disabled, and only for `Sync` types when
+/// it's enabled.
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Sync {}
+#[cfg(feature = "sync")]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(feature = "sync")]
+mod sync {
+    use std::cell::{self, RefCell};
+    use std::ops::{Deref, DerefMut};
+    use std::sync::{Condvar, Mutex};
+    use std::thread::{self, ThreadId};
+
+    /// A `RefCell` which may be shared between threads. Whichever thread borrows it first owns it
+    /// until all of its borrows are released, and other threads block until then. Within the
+    /// owning thread it behaves exactly like a `RefCell`.
+    pub struct Lock<T> {
+        ownership: Ownership,
+        value: RefCell<T>,
+    }
+
+    // The `RefCell` is only ever accessed from the thread which currently owns the lock.
+    unsafe impl<T: Send> Sync for Lock<T> {}
+
+    struct Ownership {
+        owner: Mutex<(Option<ThreadId>, usize)>,
+        released: Condvar,
+    }
+
+    impl Ownership {
+        fn acquire(&self) -> Hold<'_> {
+            let me = thread::current().id();
+            let mut owner = self.owner.lock().unwrap_or_else(|e| e.into_inner());
+            loop {
+                match owner.0 {
+                    None => {
+                        *owner = (Some(me), 1);
+                        break;
+                    }
+                    Some(id) if id == me => {
+                        owner.1 += 1;
+                        break;
+                    }
+                    Some(_) => owner = self.released.wait(owner).unwrap_or_else(|e| e.into_inner()),
+                }
+            }
+            Hold(self)
+        }
+    }
+
+    struct Hold<'a>(&'a Ownership);
+
+    impl Drop for Hold<'_> {
+        fn drop(&mut self) {
+            let mut owner = self.0.owner.lock().unwrap_or_else(|e| e.into_inner());
+            owner.1 -= 1;
+            if owner.1 == 0 {
+                owner.0 = None;
+                self.0.released.notify_one();
+            }
+        }
+    }
+
+    impl<T> Lock<T> {
+        pub fn new(value: T) -> Self {
+            Lock {
+                ownership: Ownership {
+                    owner: Mutex::new((None, 0)),
+                    released: Condvar::new(),
+                },
+                value: RefCell::new(value),
+            }
+        }
+        pub fn into_inner(self) -> T {
+            self.value.into_inner()
+        }
+        pub fn borrow(&self) -> Ref<'_, T> {
+            let hold = self.ownership.acquire();
+            Ref {
+                value: self.value.borrow(),
+                _hold: hold,
+            }
+        }
+        pub fn borrow_mut(&self) -> RefMut<'_, T> {
+            let hold = self.ownership.acquire();
+            RefMut {
+                value: self.value.borrow_mut(),
+                _hold: hold,
+            }
+        }
+    }
+
+    /// The `sync` counterpart of `std::cell::Ref`.
+    pub struct Ref<'a, T: ?Sized> {
+        // Declared first so that the borrow is released before the lock.
+        value: cell::Ref<'a, T>,
+        _hold: Hold<'a>,
+    }
+
+    impl<'a, T: ?Sized> Ref<'a, T> {
+        pub fn map<U: ?Sized, F: FnOnce(&T) -> &U>(orig: Self, f: F) -> Ref<'a, U> {
+            Ref {
+                value: cell::Ref::map(orig.value, f),
+                _hold: orig._hold,
+            }
+        }
+    }
+
+    impl<T: ?Sized> Deref for Ref<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.value
+        }
+    }
+
+    /// The `sync` counterpart of `std::cell::RefMut`.
+    pub struct RefMut<'a, T: ?Sized> {
+        value: cell::RefMut<'a, T>,
+        _hold: Hold<'a>,
+    }
+
+    impl<'a, T: ?Sized> RefMut<'a, T> {
+        pub fn map<U: ?Sized, F: FnOnce(&mut T) -> &mut U>(orig: Self, f: F) -> RefMut<'a, U> {
+            RefMut {
+                value: cell::RefMut::map(orig.value, f),
+                _hold: orig._hold,
+            }
+        }
+    }
+
+    impl<T: ?Sized> Deref for RefMut<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.value
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for RefMut<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            &mut self.value
+        }
+    }
+}
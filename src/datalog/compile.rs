@@ -0,0 +1,531 @@
+use super::parse::{parse, Aggregate, Atom, Clauses, Rule, Term};
+use super::{Error, Program};
+use crate::map::SingletonMap;
+use crate::row::{Row, Value};
+use crate::tuple::fst;
+use crate::{Collection, CreationContext, DynOp, Relation, RowRelation};
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
+use std::iter;
+
+/// Parses `src` and builds the relations for every predicate it mentions in `creation`.
+pub fn compile(creation: &mut CreationContext, src: &str) -> Result<Program, Error> {
+    let clauses = parse(src)?;
+    let arities = arities(&clauses)?;
+    for rule in clauses.rules.iter() {
+        check_safety(rule)?;
+    }
+    check_sums(&clauses)?;
+    let strata = strata(&arities, &clauses.rules)?;
+    let mut inputs = BTreeMap::new();
+    let mut input_rels = HashMap::new();
+    for pred in arities.keys() {
+        let (input, rel) = creation.create_input::<Row, isize>();
+        inputs.insert(pred.clone(), input);
+        input_rels.insert(pred.as_str(), rel.collect());
+    }
+    let mut collections: BTreeMap<&str, Collection<'static, Row>> = BTreeMap::new();
+    for stratum in strata.iter() {
+        let rules_for = |pred: &str| {
+            clauses
+                .rules
+                .iter()
+                .filter(move |r| r.head.pred == pred)
+                .collect::<Vec<_>>()
+        };
+        let outer = |pred: &str| -> RowRelation<'static> {
+            collections[pred]
+                .clone()
+                .hmap(|row| with_depth(0, row))
+                .dynamic()
+        };
+        if !stratum.recursive {
+            let pred = stratum.preds[0];
+            let arity = arities[pred];
+            let mut total = input_rels[pred].clone().dynamic();
+            for rule in rules_for(pred) {
+                total = total
+                    .concat(compile_rule(rule, &outer).project((1..=arity).collect()))
+                    .dynamic();
+            }
+            let collection = total.distinct().named(pred).collect();
+            collections.insert(pred, collection);
+        } else {
+            let mut subcontext = creation.subgraph::<usize>();
+            let mut variables = Vec::new();
+            let mut sources = HashMap::new();
+            for &pred in stratum.preds.iter() {
+                let (variable, rel) = subcontext.variable::<Row, isize>();
+                sources.insert(pred, rel.hmap(|(d, row)| with_depth(d, row)).collect());
+                variables.push((pred, variable));
+            }
+            let source = |pred: &str| match sources.get(pred) {
+                Some(rel) => rel.clone().dynamic(),
+                None => outer(pred),
+            };
+            let mut nexts = Vec::new();
+            for (pred, variable) in variables {
+                let mut total: Relation<DynOp<(Row, usize)>> =
+                    input_rels[pred].clone().hmap(|row| (row, 0)).dynamic();
+                for rule in rules_for(pred) {
+                    total = total
+                        .concat(compile_rule(rule, &source).hmap(split_depth))
+                        .dynamic();
+                }
+                let next = total.group_min().split();
+                variable.set(next.clone().hmap(|(row, d)| (d, row)));
+                nexts.push((pred, next));
+            }
+            let finalizer = subcontext.finish();
+            for (pred, next) in nexts {
+                let collection = next.leave(&finalizer).named(pred).hmap(fst).collect();
+                collections.insert(pred, collection);
+            }
+        }
+    }
+    let outputs = collections
+        .into_iter()
+        .map(|(pred, rel)| (pred.to_string(), rel.get_dyn_arrangement(creation)))
+        .collect();
+    let facts = clauses
+        .facts
+        .iter()
+        .map(|atom| {
+            let values = atom.terms.iter().map(|t| match t {
+                Term::Const(v) => v.clone(),
+                _ => unreachable!(),
+            });
+            (atom.pred.clone(), Row(values.collect()))
+        })
+        .collect();
+    Ok(Program {
+        inputs,
+        outputs,
+        facts,
+    })
+}
+
+fn arities(clauses: &Clauses) -> Result<BTreeMap<String, usize>, Error> {
+    let mut arities = BTreeMap::new();
+    let atoms = clauses.facts.iter().chain(
+        clauses
+            .rules
+            .iter()
+            .flat_map(|r| iter::once(&r.head).chain(r.body.iter().map(|l| &l.atom))),
+    );
+    for atom in atoms {
+        match arities.entry(atom.pred.clone()) {
+            btree_map::Entry::Vacant(vac) => {
+                vac.insert(atom.terms.len());
+            }
+            btree_map::Entry::Occupied(occ) => {
+                if *occ.get() != atom.terms.len() {
+                    return Err(Error::ArityMismatch {
+                        pred: atom.pred.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(arities)
+}
+
+fn vars(atom: &Atom) -> impl Iterator<Item = &String> {
+    atom.terms.iter().filter_map(|t| match t {
+        Term::Var(v) | Term::Aggregate(_, v) => Some(v),
+        Term::Const(_) => None,
+    })
+}
+
+fn check_safety(rule: &Rule) -> Result<(), Error> {
+    let bound: HashSet<&String> = rule
+        .body
+        .iter()
+        .filter(|l| !l.negated)
+        .flat_map(|l| vars(&l.atom))
+        .collect();
+    if bound.is_empty() && rule.body.iter().all(|l| l.negated) {
+        return Err(Error::NoPositiveLiteral {
+            pred: rule.head.pred.clone(),
+        });
+    }
+    let negated = rule
+        .body
+        .iter()
+        .filter(|l| l.negated)
+        .flat_map(|l| vars(&l.atom))
+        .filter(|v| !Term::is_anonymous(v));
+    for var in vars(&rule.head).chain(negated) {
+        if !bound.contains(var) {
+            return Err(Error::UnboundVariable { var: var.clone() });
+        }
+    }
+    Ok(())
+}
+
+/// Rejects `sum`s over a variable which can be bound to something other than an integer. A column
+/// can hold non-integers if a fact or rule head puts a non-integer constant there, or if a rule
+/// head copies a variable into it from such a column. (Rows inserted at run time aren't checked;
+/// `sum` ignores their non-integer values.)
+fn check_sums(clauses: &Clauses) -> Result<(), Error> {
+    let mut non_int = HashSet::new();
+    let heads = clauses
+        .facts
+        .iter()
+        .chain(clauses.rules.iter().map(|r| &r.head));
+    for atom in heads {
+        for (i, term) in atom.terms.iter().enumerate() {
+            if matches!(term, Term::Const(v) if !matches!(v, Value::Int(_))) {
+                non_int.insert((atom.pred.as_str(), i));
+            }
+        }
+    }
+    let bound_to_non_int = |non_int: &HashSet<(&str, usize)>, rule: &Rule, var: &str| {
+        rule.body.iter().filter(|l| !l.negated).any(|l| {
+            l.atom.terms.iter().enumerate().any(|(i, t)| {
+                matches!(t, Term::Var(v) if v == var)
+                    && non_int.contains(&(l.atom.pred.as_str(), i))
+            })
+        })
+    };
+    loop {
+        let mut changed = false;
+        for rule in clauses.rules.iter() {
+            for (j, term) in rule.head.terms.iter().enumerate() {
+                let var = match term {
+                    Term::Var(v) | Term::Aggregate(Aggregate::Min | Aggregate::Max, v) => v,
+                    _ => continue,
+                };
+                let col = (rule.head.pred.as_str(), j);
+                if !non_int.contains(&col) && bound_to_non_int(&non_int, rule, var) {
+                    non_int.insert(col);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    for rule in clauses.rules.iter() {
+        for term in rule.head.terms.iter() {
+            if let Term::Aggregate(Aggregate::Sum, var) = term {
+                if bound_to_non_int(&non_int, rule, var) {
+                    return Err(Error::NotSummable { var: var.clone() });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+struct Stratum<'s> {
+    preds: Vec<&'s str>,
+    recursive: bool,
+}
+
+struct Tarjan<'d> {
+    deps: &'d [Vec<usize>],
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, v: usize) {
+        self.index[v] = Some(self.next_index);
+        self.lowlink[v] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(v);
+        self.on_stack[v] = true;
+        for &w in self.deps[v].iter() {
+            match self.index[w] {
+                None => {
+                    self.visit(w);
+                    self.lowlink[v] = self.lowlink[v].min(self.lowlink[w]);
+                }
+                Some(wi) => {
+                    if self.on_stack[w] {
+                        self.lowlink[v] = self.lowlink[v].min(wi);
+                    }
+                }
+            }
+        }
+        if Some(self.lowlink[v]) == self.index[v] {
+            let mut component = Vec::new();
+            loop {
+                let w = self.stack.pop().unwrap();
+                self.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            self.components.push(component);
+        }
+    }
+}
+
+/// Splits the predicates into strongly connected components of the dependency graph, ordered so
+/// that every component comes after the components it depends on.
+fn strata<'s>(
+    arities: &'s BTreeMap<String, usize>,
+    rules: &[Rule],
+) -> Result<Vec<Stratum<'s>>, Error> {
+    let preds: Vec<&str> = arities.keys().map(String::as_str).collect();
+    let index: HashMap<&str, usize> = preds.iter().enumerate().map(|(i, &p)| (p, i)).collect();
+    let mut deps = vec![Vec::new(); preds.len()];
+    for rule in rules {
+        let head = index[rule.head.pred.as_str()];
+        for lit in rule.body.iter() {
+            deps[head].push(index[lit.atom.pred.as_str()]);
+        }
+    }
+    let mut tarjan = Tarjan {
+        deps: &deps,
+        index: vec![None; preds.len()],
+        lowlink: vec![0; preds.len()],
+        on_stack: vec![false; preds.len()],
+        stack: Vec::new(),
+        next_index: 0,
+        components: Vec::new(),
+    };
+    for v in 0..preds.len() {
+        if tarjan.index[v].is_none() {
+            tarjan.visit(v);
+        }
+    }
+    let components = tarjan.components;
+    let mut component_of = vec![0; preds.len()];
+    for (c, component) in components.iter().enumerate() {
+        for &v in component.iter() {
+            component_of[v] = c;
+        }
+    }
+    for rule in rules {
+        let head = index[rule.head.pred.as_str()];
+        let aggregated = rule
+            .head
+            .terms
+            .iter()
+            .any(|t| matches!(t, Term::Aggregate(..)));
+        for lit in rule.body.iter() {
+            let dep = index[lit.atom.pred.as_str()];
+            if (lit.negated || aggregated) && component_of[dep] == component_of[head] {
+                return Err(Error::NotStratifiable {
+                    pred: rule.head.pred.clone(),
+                });
+            }
+        }
+    }
+    Ok(components
+        .into_iter()
+        .map(|component| Stratum {
+            recursive: component.len() > 1 || deps[component[0]].contains(&component[0]),
+            preds: component.into_iter().map(|v| preds[v]).collect(),
+        })
+        .collect())
+}
+
+// Within a rule body every row carries the derivation depth of the records it was built from in
+// column 0. Inside recursive strata this is used to tier the subgraph (see
+// `CreationContext::subgraph`); elsewhere it is always 0.
+
+fn with_depth(depth: usize, row: Row) -> Row {
+    let mut values = Vec::with_capacity(row.len() + 1);
+    values.push(Value::Int(depth as i64));
+    values.extend(row.0);
+    Row(values)
+}
+
+fn split_depth(mut row: Row) -> (Row, usize) {
+    match row.0.remove(0) {
+        Value::Int(d) => (row, d as usize),
+        _ => unreachable!(),
+    }
+}
+
+fn depth(value: &Value) -> i64 {
+    match value {
+        &Value::Int(d) => d,
+        _ => unreachable!(),
+    }
+}
+
+struct Body<'a> {
+    rel: RowRelation<'a>,
+    vars: Vec<String>,
+}
+
+impl<'a> Body<'a> {
+    fn from_atom(atom: &Atom, mut rel: RowRelation<'a>) -> Self {
+        let mut vars: Vec<String> = Vec::new();
+        let mut cols = vec![0];
+        for (i, term) in atom.terms.iter().enumerate() {
+            let col = i + 1;
+            match term {
+                Term::Const(v) => rel = rel.select_eq(col, v.clone()),
+                Term::Var(v) => match vars.iter().position(|x| x == v) {
+                    Some(j) => rel = rel.select_cols_eq(cols[j + 1], col),
+                    None => {
+                        vars.push(v.clone());
+                        cols.push(col);
+                    }
+                },
+                Term::Aggregate(..) => unreachable!(),
+            }
+        }
+        if cols.len() != atom.terms.len() + 1 {
+            rel = rel.project(cols);
+        }
+        Body { rel, vars }
+    }
+    fn col(&self, var: &str) -> Option<usize> {
+        self.vars.iter().position(|x| x == var).map(|i| i + 1)
+    }
+    fn join(self, other: Body<'a>) -> Self {
+        let mut vars = self.vars.clone();
+        let mut cols = Vec::new();
+        let mut other_cols = Vec::new();
+        let mut extra = Vec::new();
+        for (j, var) in other.vars.into_iter().enumerate() {
+            match self.col(&var) {
+                Some(i) => {
+                    cols.push(i);
+                    other_cols.push(j + 1);
+                }
+                None => {
+                    extra.push(j + 1);
+                    vars.push(var);
+                }
+            }
+        }
+        let width = self.vars.len() + 1;
+        let rel = self
+            .rel
+            .join_on_columns(other.rel, cols, other_cols)
+            .hmap(move |row| {
+                let mut values = Vec::with_capacity(width + extra.len());
+                values.push(row[0].clone().max(row[width].clone()));
+                values.extend(row.0[1..width].iter().cloned());
+                values.extend(extra.iter().map(|&j| row[width + j].clone()));
+                Row(values)
+            })
+            .dynamic();
+        Body { rel, vars }
+    }
+    fn antijoin(self, other: Body<'a>) -> Self {
+        let (cols, other_cols) = other
+            .vars
+            .iter()
+            .enumerate()
+            .filter_map(|(j, var)| self.col(var).map(|i| (i, j + 1)))
+            .unzip();
+        Body {
+            rel: self.rel.antijoin_on_columns(other.rel, cols, other_cols),
+            vars: self.vars,
+        }
+    }
+}
+
+enum HeadTerm {
+    Col(usize),
+    Const(Value),
+}
+
+impl HeadTerm {
+    fn get(&self, row: &Row) -> Value {
+        match self {
+            &HeadTerm::Col(c) => row[c].clone(),
+            HeadTerm::Const(v) => v.clone(),
+        }
+    }
+}
+
+/// Produces the rows derived by `rule` (with their depth in column 0).
+fn compile_rule<'a>(rule: &Rule, source: &dyn Fn(&str) -> RowRelation<'a>) -> RowRelation<'a> {
+    let atom_body = |atom: &Atom| Body::from_atom(atom, source(&atom.pred));
+    let mut positives = rule.body.iter().filter(|l| !l.negated);
+    let first = positives.next().unwrap();
+    let mut body = atom_body(&first.atom);
+    for lit in positives {
+        body = body.join(atom_body(&lit.atom));
+    }
+    for lit in rule.body.iter().filter(|l| l.negated) {
+        body = body.antijoin(atom_body(&lit.atom));
+    }
+    let mut aggregate = None;
+    let mut terms = Vec::new();
+    for (i, term) in rule.head.terms.iter().enumerate() {
+        match term {
+            Term::Var(v) => terms.push(HeadTerm::Col(body.col(v).unwrap())),
+            Term::Const(v) => terms.push(HeadTerm::Const(v.clone())),
+            Term::Aggregate(a, v) => aggregate = Some((i, *a, body.col(v).unwrap())),
+        }
+    }
+    let (i, aggregate, col) = match aggregate {
+        None => {
+            return body
+                .rel
+                .map(move |row| {
+                    let values = iter::once(Value::Int(depth(&row[0]) + 1))
+                        .chain(terms.iter().map(|t| t.get(&row)));
+                    Row(values.collect())
+                })
+                .op_named(&rule.head.pred)
+                .dynamic()
+        }
+        Some(agg) => agg,
+    };
+    if aggregate == Aggregate::Sum {
+        // Each distinct binding of the body's variables contributes its value once, so two
+        // bindings with the same value are both counted.
+        let sums = body
+            .rel
+            .hmap(move |row| {
+                let key = Row(terms.iter().map(|t| t.get(&row)).collect());
+                (key, Row(row.0[1..].to_vec()))
+            })
+            .distinct()
+            .reduce(move |_, bindings: &HashMap<Row, isize>| {
+                let values = bindings.keys().filter_map(|b| match b[col - 1] {
+                    Value::Int(n) => Some(n),
+                    _ => None,
+                });
+                SingletonMap(Value::Int(values.sum()))
+            })
+            .op_named("sum");
+        return with_aggregate(i, sums.dynamic());
+    }
+    let keyed = body.rel.hmap(move |row| {
+        let key = Row(terms.iter().map(|t| t.get(&row)).collect());
+        (key, row[col].clone())
+    });
+    let aggregated: Relation<DynOp<(Row, Value)>> = match aggregate {
+        Aggregate::Min => keyed.group_min().dynamic(),
+        Aggregate::Max => keyed.group_max().dynamic(),
+        Aggregate::Count => keyed
+            .distinct()
+            .hmap(fst)
+            .counts()
+            .op_named("count")
+            .hmap(|(key, n)| (key, Value::Int(n as i64)))
+            .dynamic(),
+        Aggregate::Sum => unreachable!(),
+    };
+    with_aggregate(i, aggregated)
+}
+
+/// Inserts each group's aggregate into its key at column `i`.
+fn with_aggregate<'a>(i: usize, aggregated: Relation<'a, DynOp<(Row, Value)>>) -> RowRelation<'a> {
+    aggregated
+        .hmap(move |(key, value)| {
+            let mut values = key.0;
+            values.insert(i, value);
+            with_depth(0, Row(values))
+        })
+        .dynamic()
+}
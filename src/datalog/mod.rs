@@ -0,0 +1,128 @@
+//! A small Datalog dialect compiled into standing relations over dynamic `Row`s.
+//!
+//! A program is a sequence of facts and rules:
+//!
+//! ```text
+//! % Facts contain only constants. Lower-case identifiers are string constants.
+//! edge(a, b).
+//! edge("b", "c").
+//! node(a). node(b). node(c). node(d).
+//!
+//! % Upper-case identifiers are variables; `_` matches anything.
+//! reach(X, Y) :- edge(X, Y).
+//! reach(X, Z) :- reach(X, Y), edge(Y, Z).
+//!
+//! % Negation (`!` or `not`) and aggregates (`min`, `max`, `count`, `sum`) must be stratified.
+//! isolated(X) :- node(X), !edge(X, _), not edge(_, X).
+//! out_degree(X, count(Y)) :- edge(X, Y).
+//! ```
+//!
+//! Every predicate gets an `Input` and an `Arrangement` of its consolidated contents. Facts in the
+//! program text aren't inserted until `Program::insert_facts` is called. Records have set
+//! semantics: each derived row appears with multiplicity 1, and `sum` adds up the value of each
+//! distinct binding of the rule body's variables. Summing a variable that the program can bind to
+//! a non-integer is an error; non-integers inserted at run time are left out of the sum.
+//!
+//! ```
+//! use dc2::datalog;
+//! use dc2::row::Row;
+//! use dc2::CreationContext;
+//!
+//! let mut creation = CreationContext::new();
+//! let program = datalog::compile(
+//!     &mut creation,
+//!     "reach(X, Y) :- edge(X, Y).
+//!      reach(X, Z) :- reach(X, Y), edge(Y, Z).",
+//! )
+//! .unwrap();
+//! let mut context = creation.begin();
+//! let edge = |x: &str, y: &str| Row::new(vec![x.into(), y.into()]);
+//! program.input("edge").insert(&context, edge("a", "b"));
+//! program.input("edge").insert(&context, edge("b", "c"));
+//! context.commit();
+//! assert!(program.output("reach").read(&context).contains_key(&edge("a", "c")));
+//! ```
+
+mod compile;
+mod parse;
+
+pub use self::compile::compile;
+use crate::row::Row;
+use crate::{Arrangement, ExecutionContext, Input};
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Parse { line: usize, message: String },
+    ArityMismatch { pred: String },
+    UnboundVariable { var: String },
+    NoPositiveLiteral { pred: String },
+    NotStratifiable { pred: String },
+    NotSummable { var: String },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+            Error::ArityMismatch { pred } => {
+                write!(f, "Predicate `{}` used with different arities", pred)
+            }
+            Error::UnboundVariable { var } => write!(
+                f,
+                "Variable `{}` must appear in a positive body literal",
+                var
+            ),
+            Error::NoPositiveLiteral { pred } => {
+                write!(f, "A rule for `{}` has no positive body literal", pred)
+            }
+            Error::NotStratifiable { pred } => write!(
+                f,
+                "Predicate `{}` depends recursively on a negation or aggregate",
+                pred
+            ),
+            Error::NotSummable { var } => write!(
+                f,
+                "Variable `{}` is summed but can be bound to a non-integer",
+                var
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// The inputs and outputs of a compiled Datalog program, indexed by predicate name.
+pub struct Program {
+    inputs: BTreeMap<String, Input<Row>>,
+    outputs: BTreeMap<String, Arrangement<Row>>,
+    facts: Vec<(String, Row)>,
+}
+
+impl Program {
+    pub fn input(&self, pred: &str) -> &Input<Row> {
+        self.inputs
+            .get(pred)
+            .unwrap_or_else(|| panic!("Unknown predicate `{}`", pred))
+    }
+    pub fn output(&self, pred: &str) -> &Arrangement<Row> {
+        self.outputs
+            .get(pred)
+            .unwrap_or_else(|| panic!("Unknown predicate `{}`", pred))
+    }
+    pub fn inputs(&self) -> &BTreeMap<String, Input<Row>> {
+        &self.inputs
+    }
+    pub fn outputs(&self) -> &BTreeMap<String, Arrangement<Row>> {
+        &self.outputs
+    }
+    /// Stages the facts which appeared in the program text. They take effect on the next call to
+    /// `commit`.
+    pub fn insert_facts(&self, context: &ExecutionContext) {
+        for (pred, row) in self.facts.iter() {
+            self.inputs[pred].insert(context, row.clone());
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use super::Error;
+use crate::row::Value;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Aggregate {
+    Min,
+    Max,
+    Count,
+    Sum,
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum Term {
+    Var(String),
+    Const(Value),
+    Aggregate(Aggregate, String),
+}
+
+impl Term {
+    pub(super) fn is_anonymous(var: &str) -> bool {
+        var.starts_with("_#")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Atom {
+    pub(super) pred: String,
+    pub(super) terms: Vec<Term>,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Literal {
+    pub(super) negated: bool,
+    pub(super) atom: Atom,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Rule {
+    pub(super) head: Atom,
+    pub(super) body: Vec<Literal>,
+}
+
+#[derive(Default)]
+pub(super) struct Clauses {
+    pub(super) facts: Vec<Atom>,
+    pub(super) rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Var(String),
+    Int(i64),
+    Str(String),
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+    If,
+    Bang,
+}
+
+struct Lexer<'s> {
+    src: &'s str,
+    chars: Peekable<CharIndices<'s>>,
+    line: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn error(&self, message: String) -> Error {
+        Error::Parse {
+            line: self.line,
+            message,
+        }
+    }
+    fn take_while<F: Fn(char) -> bool>(&mut self, start: usize, f: F) -> &'s str {
+        let mut end = self.src.len();
+        while let Some(&(i, c)) = self.chars.peek() {
+            if !f(c) {
+                end = i;
+                break;
+            }
+            self.chars.next();
+        }
+        &self.src[start..end]
+    }
+    fn skip_line(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.chars.next();
+        }
+    }
+    fn next_token(&mut self) -> Result<Option<(Token, usize)>, Error> {
+        loop {
+            let (i, c) = match self.chars.next() {
+                None => return Ok(None),
+                Some(ic) => ic,
+            };
+            let token = match c {
+                '\n' => {
+                    self.line += 1;
+                    continue;
+                }
+                c if c.is_whitespace() => continue,
+                '%' => {
+                    self.skip_line();
+                    continue;
+                }
+                '/' if self.chars.peek().map(|&(_, c)| c) == Some('/') => {
+                    self.skip_line();
+                    continue;
+                }
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                '.' => Token::Dot,
+                '!' => Token::Bang,
+                ':' => match self.chars.next() {
+                    Some((_, '-')) => Token::If,
+                    _ => return Err(self.error("Expected `:-`".to_string())),
+                },
+                '"' => {
+                    let mut s = String::new();
+                    loop {
+                        match self.chars.next() {
+                            None => return Err(self.error("Unterminated string".to_string())),
+                            Some((_, '"')) => break,
+                            Some((_, '\\')) => match self.chars.next() {
+                                Some((_, 'n')) => s.push('\n'),
+                                Some((_, c)) => s.push(c),
+                                None => return Err(self.error("Unterminated string".to_string())),
+                            },
+                            Some((_, c)) => {
+                                if c == '\n' {
+                                    self.line += 1;
+                                }
+                                s.push(c)
+                            }
+                        }
+                    }
+                    Token::Str(s)
+                }
+                c if c == '-' || c.is_ascii_digit() => {
+                    let digits = self.take_while(i + c.len_utf8(), |c| c.is_ascii_digit());
+                    let text = &self.src[i..i + c.len_utf8() + digits.len()];
+                    let n = text
+                        .parse()
+                        .map_err(|_| self.error(format!("Invalid integer `{}`", text)))?;
+                    Token::Int(n)
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let rest =
+                        self.take_while(i + c.len_utf8(), |c| c.is_alphanumeric() || c == '_');
+                    let name = self.src[i..i + c.len_utf8() + rest.len()].to_string();
+                    if c.is_uppercase() || c == '_' {
+                        Token::Var(name)
+                    } else {
+                        Token::Ident(name)
+                    }
+                }
+                c => return Err(self.error(format!("Unexpected character `{}`", c))),
+            };
+            return Ok(Some((token, self.line)));
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    anonymous: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |&(_, line)| line)
+    }
+    fn error(&self, message: String) -> Error {
+        Error::Parse {
+            line: self.line(),
+            message,
+        }
+    }
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+    fn next(&mut self) -> Result<Token, Error> {
+        let t = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("Unexpected end of input".to_string()))?;
+        self.pos += 1;
+        Ok(t)
+    }
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        let t = self.next()?;
+        if t == expected {
+            Ok(())
+        } else {
+            self.pos -= 1;
+            Err(self.error(format!("Expected {:?}, found {:?}", expected, t)))
+        }
+    }
+    fn eat(&mut self, t: &Token) -> bool {
+        if self.peek() == Some(t) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn var(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Var(v) => Ok(self.name_var(v)),
+            t => {
+                self.pos -= 1;
+                Err(self.error(format!("Expected a variable, found {:?}", t)))
+            }
+        }
+    }
+    fn name_var(&mut self, v: String) -> String {
+        if v == "_" {
+            self.anonymous += 1;
+            format!("_#{}", self.anonymous)
+        } else {
+            v
+        }
+    }
+    fn term(&mut self, in_head: bool) -> Result<Term, Error> {
+        Ok(match self.next()? {
+            Token::Var(v) => Term::Var(self.name_var(v)),
+            Token::Int(n) => Term::Const(Value::Int(n)),
+            Token::Str(s) => Term::Const(Value::Str(s)),
+            Token::Ident(ref s) if s == "true" => Term::Const(Value::Bool(true)),
+            Token::Ident(ref s) if s == "false" => Term::Const(Value::Bool(false)),
+            Token::Ident(s) => {
+                if self.peek() != Some(&Token::LParen) {
+                    Term::Const(Value::Str(s))
+                } else {
+                    let aggregate = match s.as_str() {
+                        "min" => Aggregate::Min,
+                        "max" => Aggregate::Max,
+                        "count" => Aggregate::Count,
+                        "sum" => Aggregate::Sum,
+                        _ => return Err(self.error(format!("Unknown aggregate `{}`", s))),
+                    };
+                    if !in_head {
+                        return Err(self.error("Aggregates may only appear in heads".to_string()));
+                    }
+                    self.expect(Token::LParen)?;
+                    let v = self.var()?;
+                    self.expect(Token::RParen)?;
+                    Term::Aggregate(aggregate, v)
+                }
+            }
+            t => {
+                self.pos -= 1;
+                return Err(self.error(format!("Expected a term, found {:?}", t)));
+            }
+        })
+    }
+    fn atom(&mut self, in_head: bool) -> Result<Atom, Error> {
+        let pred = match self.next()? {
+            Token::Ident(pred) => pred,
+            t => {
+                self.pos -= 1;
+                return Err(self.error(format!("Expected a predicate, found {:?}", t)));
+            }
+        };
+        let mut terms = Vec::new();
+        if self.eat(&Token::LParen) && !self.eat(&Token::RParen) {
+            loop {
+                terms.push(self.term(in_head)?);
+                if self.eat(&Token::RParen) {
+                    break;
+                }
+                self.expect(Token::Comma)?;
+            }
+        }
+        Ok(Atom { pred, terms })
+    }
+    fn literal(&mut self) -> Result<Literal, Error> {
+        let negated = self.eat(&Token::Bang) || {
+            let is_not = self.peek() == Some(&Token::Ident("not".to_string()))
+                && matches!(self.tokens.get(self.pos + 1), Some((Token::Ident(_), _)));
+            if is_not {
+                self.pos += 1;
+            }
+            is_not
+        };
+        Ok(Literal {
+            negated,
+            atom: self.atom(false)?,
+        })
+    }
+    fn clauses(&mut self) -> Result<Clauses, Error> {
+        let mut clauses = Clauses::default();
+        while self.peek().is_some() {
+            let head = self.atom(true)?;
+            let aggregates = head
+                .terms
+                .iter()
+                .filter(|t| matches!(t, Term::Aggregate(..)))
+                .count();
+            if aggregates > 1 {
+                return Err(self.error("At most one aggregate is allowed per head".to_string()));
+            }
+            if self.eat(&Token::If) {
+                let mut body = vec![self.literal()?];
+                while self.eat(&Token::Comma) {
+                    body.push(self.literal()?);
+                }
+                self.expect(Token::Dot)?;
+                clauses.rules.push(Rule { head, body });
+            } else {
+                self.expect(Token::Dot)?;
+                if head.terms.iter().any(|t| !matches!(t, Term::Const(_))) {
+                    return Err(Error::Parse {
+                        line: self.tokens[self.pos - 1].1,
+                        message: format!("Fact `{}` must only contain constants", head.pred),
+                    });
+                }
+                clauses.facts.push(head);
+            }
+        }
+        Ok(clauses)
+    }
+}
+
+pub(super) fn parse(src: &str) -> Result<Clauses, Error> {
+    let mut lexer = Lexer {
+        src,
+        chars: src.char_indices().peekable(),
+        line: 1,
+    };
+    let mut tokens = Vec::new();
+    while let Some(t) = lexer.next_token()? {
+        tokens.push(t);
+    }
+    Parser {
+        tokens,
+        pos: 0,
+        anonymous: 0,
+    }
+    .clauses()
+}
@@ -1,18 +1,22 @@
-#![feature(map_first_last)]
-#![feature(type_alias_impl_trait)]
-
 mod convenience_operators;
 mod core;
+pub mod datalog;
 pub mod map;
+pub mod row;
+mod rule;
 mod tuple;
+pub mod weight;
 
 pub use self::convenience_operators::{
-    Collection, DynReceiver, MapMapArrangement, MappingArrangement, OrderedArrangement,
+    Collection, DynReceiver, MapMapArrangement, MappingArrangement, OrderedArrangement, RowRelation,
 };
 pub use self::core::{
-    borrow, emptyable, key, monoid, subgraph, Arrangement, CreationContext, DynOp,
-    ExecutionContext, Input, IsReduce, Op, Receiver, ReduceOutput, Relation,
+    borrow, emptyable, key, monoid, semiring, shared, subgraph, Arranged, Arrangement, Compaction,
+    CreationContext, DynOp, ExecutionContext, Input, InputCheck, Interned, Interner, IsReduce,
+    NegativeMultiplicity, Op, Receiver, ReduceOutput, Relation, StaticTable,
 };
+#[cfg(feature = "sync")]
+pub use self::core::{Flush, Upstream};
 
 #[cfg(test)]
 mod tests;
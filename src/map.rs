@@ -3,7 +3,8 @@ use crate::emptyable::Emptyable;
 use crate::monoid::Monoid;
 use std::cmp::Ordering;
 use std::collections::{btree_map, BTreeMap, HashMap};
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
+use std::iter;
 use std::mem;
 use std::option;
 
@@ -205,7 +206,9 @@ impl<K: Ord, V> IsDiscardMap<K, V> for BTreeMap<K, V> {
     }
 }
 
-impl<K1: Eq + Hash, K2, V, M: IsRemoveMap<K2, V>> IsRemoveMap<(K1, K2), V> for HashMap<K1, M> {
+impl<K1: Eq + Hash, K2, V, M: IsRemoveMap<K2, V>, S: BuildHasher + Default> IsRemoveMap<(K1, K2), V>
+    for HashMap<K1, M, S>
+{
     fn remove(&mut self, (k1, k2): &(K1, K2)) -> Option<V> {
         let m = self.get_mut(k1)?;
         let v = m.remove(k2)?;
@@ -238,10 +241,18 @@ impl HasOne for &isize {
     }
 }
 
+fn assert_one<K, R: HasOne>((k, r): (K, R)) -> K {
+    if r.is_one() {
+        k
+    } else {
+        panic!("Not a one")
+    }
+}
+
 impl<K, R: HasOne, I: Iterator<Item = (K, R)>> AssertOnes for I {
-    type Result = impl Iterator<Item = K>;
+    type Result = iter::Map<I, fn((K, R)) -> K>;
 
     fn assert_ones(self) -> Self::Result {
-        self.map(|(k, r)| if r.is_one() { k } else { panic!("Not a one") })
+        self.map(assert_one)
     }
 }
@@ -0,0 +1,118 @@
+//! Dynamically typed records for relations whose schemas are only known at run-time. See the
+//! column-based operators on `Relation<'a, C: Op<D = Row>>`.
+
+use std::fmt::{self, Display};
+use std::ops::Index;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+fn fmt_values(f: &mut fmt::Formatter, values: &[Value]) -> fmt::Result {
+    for (i, v) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", v)?;
+    }
+    Ok(())
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Tuple(vs) => {
+                write!(f, "(")?;
+                fmt_values(f, vs)?;
+                write!(f, ")")
+            }
+            Value::List(vs) => {
+                write!(f, "[")?;
+                fmt_values(f, vs)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Row(pub Vec<Value>);
+
+impl Row {
+    pub fn new(values: Vec<Value>) -> Self {
+        Row(values)
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn get(&self, col: usize) -> Option<&Value> {
+        self.0.get(col)
+    }
+    /// Builds a new row out of the given columns of this one (in the given order, possibly with
+    /// repeats). Panics if any column is out of range.
+    pub fn project(&self, cols: &[usize]) -> Row {
+        Row(cols.iter().map(|&c| self[c].clone()).collect())
+    }
+    /// Appends the columns of `other` to the end of this row.
+    pub fn concat(mut self, other: Row) -> Row {
+        self.0.extend(other.0);
+        self
+    }
+}
+
+impl Index<usize> for Row {
+    type Output = Value;
+
+    fn index(&self, col: usize) -> &Value {
+        &self.0[col]
+    }
+}
+
+impl From<Vec<Value>> for Row {
+    fn from(values: Vec<Value>) -> Self {
+        Row(values)
+    }
+}
+
+impl Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        fmt_values(f, &self.0)?;
+        write!(f, ")")
+    }
+}
@@ -0,0 +1,139 @@
+/// Builds a relation from Datalog-like rules over statically typed relations, taking care of
+/// keying records for each join, semijoin and antijoin.
+///
+/// A rule has the form `head <- item, item, ...` where `head` is a single token tree (usually a
+/// parenthesized tuple) built from the variables bound in the body. The first body item is
+/// `pattern in relation`. Each subsequent item is one of:
+///
+/// - `[x, y, ...] pattern in relation`: joins with `relation`, matching records where the listed
+///   variables have the same values on both sides. They must be bound both by an earlier item and
+///   by `pattern`. The key is the listed variable if there is one, or a tuple of them otherwise.
+/// - `[x, y, ...] ?relation`: keeps only records whose key is present in `relation`.
+/// - `[x, y, ...] !relation`: keeps only records whose key is absent from `relation`.
+/// - `if condition`: keeps only records for which `condition` holds. The variables are
+///   references into the record here, so comparing one with a value takes a `*`.
+///
+/// Several rules separated by `;` are concatenated together.
+///
+/// The join keys have to be listed because a declarative macro can't tell which variables two
+/// patterns share. Keys and conditions are computed from a borrowed record, so only the key's
+/// variables are cloned.
+///
+/// Example:
+///
+/// ```
+/// use dc2::{rule, CreationContext};
+/// use std::collections::HashMap;
+///
+/// let creation = CreationContext::new();
+/// let (edges_inp, edges) = creation.create_input::<(char, char), isize>();
+/// let (blocked_inp, blocked) = creation.create_input::<char, isize>();
+/// let edges = edges.split();
+/// let two_hops = rule!(
+///     (a, c) <- (a, b) in edges.clone(), [b] (b, c) in edges.clone(), [c] !blocked, if a != c && *c != 'x';
+///     (a, b) <- (a, b) in edges
+/// )
+/// .get_arrangement::<HashMap<_, _>>(&creation);
+///
+/// let mut context = creation.begin();
+/// edges_inp.insert(&context, ('a', 'b'));
+/// edges_inp.insert(&context, ('b', 'c'));
+/// edges_inp.insert(&context, ('b', 'd'));
+/// blocked_inp.insert(&context, 'd');
+/// context.commit();
+///
+/// assert_eq!(
+///     &*two_hops.read(&context),
+///     &vec![(('a', 'b'), 1), (('b', 'c'), 1), (('b', 'd'), 1), (('a', 'c'), 1)]
+///         .into_iter()
+///         .collect()
+/// );
+/// ```
+#[macro_export]
+macro_rules! rule {
+    // Splits the input into rules (on `;`) and each rule into items (on `,`).
+    (@split [$($rules:tt)*] [$($items:tt)*] [$($cur:tt)*]) => {
+        $crate::rule!(@concat $($rules)* [$($items)* ($($cur)*)])
+    };
+    (@split [$($rules:tt)*] [$($items:tt)*] [$($cur:tt)*] ; $($rest:tt)*) => {
+        $crate::rule!(@split [$($rules)* [$($items)* ($($cur)*)]] [] [] $($rest)*)
+    };
+    (@split $rules:tt [$($items:tt)*] [$($cur:tt)*] , $($rest:tt)*) => {
+        $crate::rule!(@split $rules [$($items)* ($($cur)*)] [] $($rest)*)
+    };
+    (@split $rules:tt $items:tt [$($cur:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::rule!(@split $rules $items [$($cur)* $t] $($rest)*)
+    };
+
+    (@concat $first:tt $($rest:tt)*) => {
+        $crate::rule!(@rule $first)$(.concat($crate::rule!(@rule $rest)))*
+    };
+
+    (@rule [($head:tt <- $p:pat in $rel:expr) $($items:tt)*]) => {
+        $crate::rule!(@items $head [$p] ($rel) $($items)*)
+    };
+
+    (@items $head:tt [$($ps:pat),*] ($acc:expr)) => {
+        $acc.map(move |rec| $crate::rule!(@bind rec; [$($ps),*]; $head))
+    };
+    (@items $head:tt [$($ps:pat),*] ($acc:expr) ([$($key:ident),+] ! $rel:expr) $($items:tt)*) => {
+        $crate::rule!(
+            @items $head [$($ps),*]
+            (
+                $acc.antijoin_on($rel, move |rec| {
+                    $crate::rule!(@bind rec; [$($ps),*]; ($($key.clone()),+))
+                })
+            )
+            $($items)*
+        )
+    };
+    (@items $head:tt [$($ps:pat),*] ($acc:expr) ([$($key:ident),+] ? $rel:expr) $($items:tt)*) => {
+        $crate::rule!(
+            @items $head [$($ps),*]
+            (
+                $acc.semijoin_on($rel, move |rec| {
+                    $crate::rule!(@bind rec; [$($ps),*]; ($($key.clone()),+))
+                })
+            )
+            $($items)*
+        )
+    };
+    (@items $head:tt [$($ps:pat),*] ($acc:expr) ([$($key:ident),+] $p:pat in $rel:expr) $($items:tt)*) => {
+        $crate::rule!(
+            @items $head [$p, $($ps),*]
+            (
+                $acc.join_on(
+                    $rel,
+                    move |rec| $crate::rule!(@bind rec; [$($ps),*]; ($($key.clone()),+)),
+                    move |rec| $crate::rule!(@bind rec; [$p]; ($($key.clone()),+)),
+                )
+            )
+            $($items)*
+        )
+    };
+    (@items $head:tt [$($ps:pat),*] ($acc:expr) (if $cond:expr) $($items:tt)*) => {
+        $crate::rule!(
+            @items $head [$($ps),*]
+            ($acc.filter(move |rec| $crate::rule!(@bind rec; [$($ps),*]; $cond)))
+            $($items)*
+        )
+    };
+
+    // Destructures a record built up from nested `(earlier, latest)` pairs using the patterns of
+    // the body items (latest first) and evaluates `$body` with all of them in scope.
+    (@bind $rec:expr; [$p:pat]; $body:expr) => {{
+        #[allow(unused_variables)]
+        let $p = $rec;
+        $body
+    }};
+    (@bind $rec:expr; [$p:pat, $($ps:pat),+]; $body:expr) => {{
+        let (earlier, latest) = $rec;
+        #[allow(unused_variables)]
+        let $p = latest;
+        $crate::rule!(@bind earlier; [$($ps),+]; $body)
+    }};
+
+    ($($t:tt)*) => {
+        $crate::rule!(@split [] [] [] $($t)*)
+    };
+}
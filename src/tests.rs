@@ -1,5 +1,10 @@
+use crate::datalog;
 use crate::key::Key;
-use crate::{Arrangement, Collection, CreationContext};
+use crate::row::{Row, Value};
+use crate::{
+    Arrangement, Collection, CreationContext, ExecutionContext, InputCheck, NegativeMultiplicity,
+    ReduceOutput, StaticTable,
+};
 use std::collections::HashMap;
 
 #[test]
@@ -345,3 +350,1084 @@ fn test_transitive_closure_cyclic() {
         .collect()
     );
 }
+
+#[test]
+fn test_input_contents() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_tracked_input::<char, isize>();
+    let outp = rel.get_arrangement::<HashMap<_, _>>(&creation);
+    let mut execution = creation.begin();
+    input.insert(&execution, 'a');
+    input.insert(&execution, 'b');
+    assert!(input.contents(&execution).is_empty());
+    execution.commit();
+    assert_eq!(
+        &*input.contents(&execution),
+        &vec![('a', 1), ('b', 1)].into_iter().collect()
+    );
+    input.replace_all(&execution, vec![('b', 1), ('c', 2)]);
+    execution.commit();
+    assert_eq!(
+        &*input.contents(&execution),
+        &vec![('b', 1), ('c', 2)].into_iter().collect()
+    );
+    assert_eq!(
+        &*outp.read(&execution),
+        &vec![('b', 1), ('c', 2)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_checked_input() {
+    let creation = CreationContext::new();
+    let (errors, _) = creation.create_checked_input::<char, isize>(InputCheck::Error);
+    let (clamped, rel) = creation.create_checked_input::<char, isize>(InputCheck::Clamp);
+    let outp = rel.distinct().get_arrangement::<HashMap<_, _>>(&creation);
+    let mut execution = creation.begin();
+    errors.insert(&execution, 'a');
+    assert_eq!(errors.try_update(&execution, 'a', -1), Ok(()));
+    assert_eq!(
+        errors.try_update(&execution, 'a', -1),
+        Err(NegativeMultiplicity {
+            record: 'a',
+            multiplicity: -1
+        })
+    );
+    clamped.insert(&execution, 'a');
+    clamped.delete(&execution, 'b');
+    execution.commit();
+    clamped.update(&execution, 'a', -3);
+    clamped.insert(&execution, 'b');
+    execution.commit();
+    assert_eq!(
+        &*clamped.contents(&execution),
+        &vec![('b', 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*outp.read(&execution),
+        &vec![('b', 1)].into_iter().collect()
+    );
+    errors.insert(&execution, 'c');
+    assert_eq!(
+        errors.try_replace_all(&execution, vec![('b', 1), ('a', -2)]),
+        Err(NegativeMultiplicity {
+            record: 'a',
+            multiplicity: -2
+        })
+    );
+    clamped.replace_all(&execution, vec![('b', -1), ('d', 1)]);
+    execution.commit();
+    assert_eq!(
+        &*errors.contents(&execution),
+        &vec![('c', 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*clamped.contents(&execution),
+        &vec![('d', 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_row_operators() {
+    fn row(name: &str, n: i64) -> Row {
+        Row::new(vec![Value::from(name), Value::from(n)])
+    }
+    let creation = CreationContext::new();
+    let (people_input, people) = creation.create_input::<Row, isize>();
+    let (ages_input, ages) = creation.create_input::<Row, isize>();
+    let ages = ages.split();
+    let joined = people
+        .join_on_columns(ages.clone(), vec![0], vec![0])
+        .project(vec![0, 1, 3])
+        .get_arrangement::<HashMap<_, _>>(&creation);
+    let thirty = ages
+        .select_eq(1, Value::Int(30))
+        .project(vec![0])
+        .get_arrangement::<HashMap<_, _>>(&creation);
+    let mut execution = creation.begin();
+    people_input.insert(&execution, row("alice", 1));
+    people_input.insert(&execution, row("bob", 2));
+    ages_input.insert(&execution, row("alice", 30));
+    ages_input.insert(&execution, row("carol", 30));
+    ages_input.insert(&execution, row("bob", 40));
+    execution.commit();
+    assert_eq!(
+        &*joined.read(&execution),
+        &vec![
+            (Row::new(vec!["alice".into(), 1.into(), 30.into()]), 1),
+            (Row::new(vec!["bob".into(), 2.into(), 40.into()]), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert_eq!(
+        &*thirty.read(&execution),
+        &vec![
+            (Row::new(vec!["alice".into()]), 1),
+            (Row::new(vec!["carol".into()]), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+#[test]
+fn test_datalog() {
+    fn row(values: &[&str]) -> Row {
+        Row::new(values.iter().map(|&v| v.into()).collect())
+    }
+    let mut creation = CreationContext::new();
+    let program = datalog::compile(
+        &mut creation,
+        r#"
+        node(a). node(b). node(c). node(d).
+        reach(X, Y) :- edge(X, Y).
+        reach(X, Z) :- reach(X, Y), edge(Y, Z).
+        unreachable(X, Y) :- node(X), node(Y), !reach(X, Y).
+        isolated(X) :- node(X), not edge(X, _), !edge(_, X).
+        out_degree(X, count(Y)) :- edge(X, Y).
+        weight(a, b, 3). weight(b, c, 1). weight(a, c, 5).
+        lightest(X, min(W)) :- weight(X, _, W), edge(X, _).
+        item(a, p, 2). item(a, q, 2). item(b, p, 4).
+        total(X, sum(C)) :- item(X, _, C).
+        "#,
+    )
+    .unwrap();
+    let mut context = creation.begin();
+    program.insert_facts(&context);
+    let edge = program.input("edge");
+    edge.insert(&context, row(&["a", "b"]));
+    edge.insert(&context, row(&["b", "c"]));
+    edge.insert(&context, row(&["c", "b"]));
+    context.commit();
+    let reach = |context: &ExecutionContext, x, y| {
+        program
+            .output("reach")
+            .read(context)
+            .contains_key(&row(&[x, y]))
+    };
+    assert!(reach(&context, "a", "c"));
+    assert!(reach(&context, "b", "b"));
+    assert!(!reach(&context, "c", "a"));
+    assert!(program
+        .output("unreachable")
+        .read(&context)
+        .contains_key(&row(&["c", "a"])));
+    assert_eq!(
+        &*program.output("isolated").read(&context),
+        &vec![(row(&["d"]), 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*program.output("out_degree").read(&context),
+        &vec![
+            (Row::new(vec!["a".into(), 1.into()]), 1),
+            (Row::new(vec!["b".into(), 1.into()]), 1),
+            (Row::new(vec!["c".into(), 1.into()]), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert_eq!(
+        &*program.output("lightest").read(&context),
+        &vec![
+            (Row::new(vec!["a".into(), 3.into()]), 1),
+            (Row::new(vec!["b".into(), 1.into()]), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+
+    assert_eq!(
+        &*program.output("total").read(&context),
+        &vec![
+            (Row::new(vec!["a".into(), 4.into()]), 1),
+            (Row::new(vec!["b".into(), 4.into()]), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+
+    edge.delete(&context, row(&["a", "b"]));
+    context.commit();
+    assert!(!reach(&context, "a", "c"));
+    assert!(reach(&context, "b", "b"));
+    assert_eq!(program.output("lightest").read(&context).len(), 1);
+}
+
+#[test]
+fn test_datalog_errors() {
+    let compile = |src| datalog::compile(&mut CreationContext::new(), src).err();
+    assert_eq!(
+        compile("p(X) :- q(X), !p(X)."),
+        Some(datalog::Error::NotStratifiable {
+            pred: "p".to_string()
+        })
+    );
+    assert_eq!(
+        compile("p(X, Y) :- q(X)."),
+        Some(datalog::Error::UnboundVariable {
+            var: "Y".to_string()
+        })
+    );
+    assert_eq!(
+        compile("p(X) :- q(X). q(X, Y) :- p(X), p(Y)."),
+        Some(datalog::Error::ArityMismatch {
+            pred: "q".to_string()
+        })
+    );
+    assert_eq!(
+        compile("q(a, b). r(X, Y) :- q(X, Y). p(X, sum(Y)) :- r(X, Y)."),
+        Some(datalog::Error::NotSummable {
+            var: "Y".to_string()
+        })
+    );
+    assert!(matches!(
+        compile("p(X) :- \n q(X"),
+        Some(datalog::Error::Parse { line: 2, .. })
+    ));
+}
+
+#[test]
+fn test_rule_macro() {
+    let mut creation = CreationContext::new();
+    let (edge_input, edges) = creation.create_input::<(char, char), isize>();
+    let (start_input, starts) = creation.create_input::<char, isize>();
+    let edges = edges.split();
+    let mut subcontext = creation.subgraph::<usize>();
+    let (closure_var, closure) = subcontext.variable::<(char, char), isize>();
+    let closure_n = crate::rule!(
+        ((l, r), d + 1) <- (d, (l, m)) in closure, [m] (m, r) in edges.clone();
+        ((l, r), 1) <- (l, r) in edges.clone()
+    )
+    .group_min()
+    .split();
+    closure_var.set(closure_n.clone().map(|(e, d)| (d, e)));
+    let from_starts = crate::rule!(
+        (l, r, d) <- ((l, r), d) in closure_n.leave(&subcontext.finish()), [l] ?starts
+    )
+    .get_arrangement::<HashMap<_, _>>(&creation);
+    let mutual = crate::rule!((a, b) <- (a, b) in edges.clone(), [a, b] (b, a) in edges)
+        .get_arrangement::<HashMap<_, _>>(&creation);
+    let mut execution = creation.begin();
+    edge_input.insert(&execution, ('A', 'B'));
+    edge_input.insert(&execution, ('B', 'C'));
+    edge_input.insert(&execution, ('C', 'D'));
+    edge_input.insert(&execution, ('D', 'C'));
+    start_input.insert(&execution, 'B');
+    execution.commit();
+    assert_eq!(
+        &*from_starts.read(&execution),
+        &vec![(('B', 'C', 1), 1), (('B', 'D', 2), 1)]
+            .into_iter()
+            .collect()
+    );
+    assert_eq!(
+        &*mutual.read(&execution),
+        &vec![(('C', 'D'), 1), (('D', 'C'), 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_arrange_by_key() {
+    let creation = CreationContext::new();
+    let (big_input, big) = creation.create_input::<(usize, char), isize>();
+    let (left_input, left) = creation.create_input::<(usize, &str), isize>();
+    let left = left.split();
+    let (keys_input, keys) = creation.create_input::<usize, isize>();
+    let keys = keys.split();
+    let big = big.split();
+    let arranged = big.clone().arrange_by_key();
+    let shared_join = arranged.join(left.clone()).get_dyn_arrangement(&creation);
+    let shared_semijoin = arranged
+        .semijoin(keys.clone())
+        .get_dyn_arrangement(&creation);
+    let shared_antijoin = arranged
+        .antijoin(keys.clone())
+        .get_dyn_arrangement(&creation);
+    let plain_join = big.clone().join(left).get_dyn_arrangement(&creation);
+    let plain_semijoin = big
+        .clone()
+        .semijoin(keys.clone())
+        .get_dyn_arrangement(&creation);
+    let plain_antijoin = big.antijoin(keys).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    let check = |execution: &ExecutionContext| {
+        assert_eq!(&*shared_join.read(execution), &*plain_join.read(execution));
+        assert_eq!(
+            &*shared_semijoin.read(execution),
+            &*plain_semijoin.read(execution)
+        );
+        assert_eq!(
+            &*shared_antijoin.read(execution),
+            &*plain_antijoin.read(execution)
+        );
+    };
+    big_input.insert(&execution, (1, 'a'));
+    big_input.insert(&execution, (1, 'b'));
+    big_input.insert(&execution, (2, 'c'));
+    left_input.insert(&execution, (1, "one"));
+    keys_input.insert(&execution, 2);
+    execution.commit();
+    check(&execution);
+    assert_eq!(
+        &*shared_join.read(&execution),
+        &vec![((1, ('a', "one")), 1), ((1, ('b', "one")), 1)]
+            .into_iter()
+            .collect()
+    );
+    big_input.delete(&execution, (1, 'a'));
+    big_input.insert(&execution, (3, 'd'));
+    left_input.insert(&execution, (3, "three"));
+    keys_input.delete(&execution, 2);
+    keys_input.insert(&execution, 1);
+    execution.commit();
+    check(&execution);
+    assert_eq!(
+        &*shared_antijoin.read(&execution),
+        &vec![((2, 'c'), 1), ((3, 'd'), 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_compact() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<(usize, usize), isize>();
+    let rel = rel.split();
+    let outp = rel
+        .clone()
+        .join(rel.map(|(k, x)| (k, x + 1)))
+        .map(|(k, _)| k)
+        .distinct()
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    for i in 0..1000 {
+        input.insert(&execution, (i % 10, i));
+    }
+    execution.commit();
+    assert_eq!(outp.read(&execution).len(), 10);
+    for i in 10..1000 {
+        input.delete(&execution, (i % 10, i));
+    }
+    execution.commit();
+    assert_eq!(outp.read(&execution).len(), 10);
+    assert!(execution.compact().released > 0);
+    assert_eq!(execution.compact().released, 0);
+    input.delete(&execution, (3, 3));
+    execution.commit();
+    assert_eq!(outp.read(&execution).len(), 9);
+}
+
+#[test]
+fn test_compact_diamonds() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<usize, isize>();
+    let mut rel = rel.dynamic().split();
+    // Each level reaches the previous one through two receivers, so walking upstream through
+    // every receiver would visit the input 2^30 times.
+    for _ in 0..30 {
+        rel = rel.clone().concat(rel).dynamic().split();
+    }
+    let outp = rel.distinct().get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    for i in 0..1000 {
+        input.insert(&execution, i);
+    }
+    execution.commit();
+    assert_eq!(outp.read(&execution).len(), 1000);
+    for i in 10..1000 {
+        input.delete(&execution, i);
+    }
+    execution.commit();
+    assert_eq!(outp.read(&execution).len(), 10);
+    let released = execution.compact().released;
+    assert!(released > 0);
+    assert!(released < 100_000);
+    assert_eq!(execution.compact().released, 0);
+}
+
+#[test]
+fn test_interned_keys() {
+    let creation = CreationContext::new();
+    let names = creation.interner::<String>();
+    let (ages_input, ages) = creation.create_input::<(String, usize), isize>();
+    let (cities_input, cities) = creation.create_input::<(String, String), isize>();
+    let outp = ages
+        .intern_keys(&names)
+        .join(cities.intern_keys(&names))
+        .map(|(name, (age, city))| (name.to_string(), age, city))
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    let same_names = execution.interner::<String>();
+    assert_eq!(
+        names.intern("x".to_string()),
+        same_names.intern("x".to_string())
+    );
+    assert_ne!(names.intern("x".to_string()), names.intern("y".to_string()));
+    ages_input.insert(&execution, ("alice".to_string(), 30));
+    ages_input.insert(&execution, ("bob".to_string(), 40));
+    cities_input.insert(&execution, ("alice".to_string(), "Paris".to_string()));
+    execution.commit();
+    assert_eq!(
+        &*outp.read(&execution),
+        &vec![(("alice".to_string(), 30, "Paris".to_string()), 1)]
+            .into_iter()
+            .collect()
+    );
+    // "x", "y", "alice" and "bob".
+    assert_eq!(names.len(), 4);
+    ages_input.delete(&execution, ("bob".to_string(), 40));
+    execution.commit();
+    outp.read(&execution);
+    assert_eq!(execution.compact().forgotten, 3);
+    assert_eq!(names.len(), 1);
+}
+
+#[test]
+fn test_join_static() {
+    let countries: StaticTable<&str, &str> =
+        vec![("fr", "France"), ("de", "Germany"), ("de", "Deutschland")]
+            .into_iter()
+            .collect();
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<(&str, usize), isize>();
+    let rel = rel.split();
+    let joined = rel
+        .clone()
+        .join_static(&countries)
+        .get_dyn_arrangement(&creation);
+    let looked_up = rel
+        .lookup(&countries, |&(code, _)| code)
+        .map(|((_, id), name)| (id, name))
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    input.insert(&execution, ("fr", 1));
+    input.insert(&execution, ("de", 2));
+    input.insert(&execution, ("it", 3));
+    execution.commit();
+    assert_eq!(
+        &*joined.read(&execution),
+        &vec![
+            (("fr", (1, "France")), 1),
+            (("de", (2, "Germany")), 1),
+            (("de", (2, "Deutschland")), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    input.delete(&execution, ("de", 2));
+    execution.commit();
+    assert_eq!(
+        &*looked_up.read(&execution),
+        &vec![((1, "France"), 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_weights() {
+    use crate::monoid::Monoid;
+    use crate::weight::{Fixed, Optional, Pair};
+    use std::ops::Mul;
+
+    // Joins a single record on each side with the given multiplicities and returns the
+    // multiplicity of the result.
+    fn join_weight<L: Monoid + Mul<R, Output = O>, R: Monoid, O: Monoid>(l: L, r: R) -> Option<O> {
+        let creation = CreationContext::new();
+        let (left_input, left) = creation.create_input::<(char, usize), L>();
+        let (right_input, right) = creation.create_input::<(char, usize), R>();
+        let outp = left.join(right).get_dyn_arrangement(&creation);
+        let mut execution = creation.begin();
+        left_input.update(&execution, ('a', 1), l);
+        right_input.update(&execution, ('a', 2), r);
+        execution.commit();
+        let result = outp.read(&execution).get(&('a', (1, 2))).cloned();
+        result
+    }
+    assert_eq!(join_weight(3i8, 2i8), Some(6i8));
+    assert_eq!(join_weight(3i32, -2i32), Some(-6i32));
+    assert_eq!(join_weight(1i128 << 70, 2i128), Some(1i128 << 71));
+    assert_eq!(
+        join_weight(Pair(2i64, 3isize), Pair(5i64, -1isize)),
+        Some(Pair(10i64, -3isize))
+    );
+    assert_eq!(
+        join_weight(Optional(Some(2i16)), Optional(Some(4i16))),
+        Some(Optional(Some(8i16)))
+    );
+    assert_eq!(
+        join_weight(Fixed::<2>::from_units(150), Fixed::<2>::from_units(250)),
+        Some(Fixed::<2>::from_units(375))
+    );
+    assert_eq!(Fixed::<2>::from_units(-375).to_string(), "-3.75");
+    assert!(std::panic::catch_unwind(|| {
+        let mut f = Fixed::<2>::from_units(i64::MAX);
+        f += Fixed::<2>::from_units(1);
+    })
+    .is_err());
+    // Zero-sum updates still cancel out.
+    let mut p = Pair(1i8, 0i64);
+    p += -Pair(1i8, 0i64);
+    assert!(p.is_zero());
+    let mut o = Optional(Some(3i32));
+    o += Optional(Some(-3i32));
+    assert_eq!(o, Optional(None));
+}
+
+#[test]
+fn test_semiring_weights() {
+    use crate::semiring::MinPlus;
+
+    let creation = CreationContext::new();
+    let (edges_input, edges) = creation.create_input::<(char, (char, MinPlus<u32>)), isize>();
+    let edges = edges.split();
+    let two_hops = edges
+        .clone()
+        .map(|(x, (y, w))| (y, (x, w)))
+        .join_weighted(edges)
+        .map(|((_, (x, z)), w)| ((x, z), w))
+        .semiring_sum()
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    edges_input.insert(&execution, ('a', ('b', MinPlus::new(1))));
+    edges_input.insert(&execution, ('b', ('d', MinPlus::new(1))));
+    edges_input.insert(&execution, ('a', ('c', MinPlus::new(2))));
+    edges_input.insert(&execution, ('c', ('d', MinPlus::new(5))));
+    execution.commit();
+    assert_eq!(
+        &*two_hops.read(&execution),
+        &vec![((('a', 'd'), MinPlus::new(2)), 1)]
+            .into_iter()
+            .collect()
+    );
+    edges_input.delete(&execution, ('a', ('b', MinPlus::new(1))));
+    execution.commit();
+    assert_eq!(
+        &*two_hops.read(&execution),
+        &vec![((('a', 'd'), MinPlus::new(7)), 1)]
+            .into_iter()
+            .collect()
+    );
+    edges_input.delete(&execution, ('c', ('d', MinPlus::new(5))));
+    execution.commit();
+    assert!(two_hops.read(&execution).is_empty());
+}
+
+#[test]
+fn test_float_weights() {
+    use crate::weight::{Float, Tolerance};
+
+    let creation = CreationContext::new();
+    let (scores_input, scores) = creation.create_input::<(char, usize), Float>();
+    let (probs_input, probs) = creation.create_input::<(char, &str), Float>();
+    let totals = scores.get_dyn_arrangement(&creation);
+    let (scores2_input, scores2) = creation.create_input::<(char, usize), Float>();
+    let joined = scores2.join(probs).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    for _ in 0..10 {
+        scores_input.update(&execution, ('a', 1), Float::new(0.1));
+    }
+    scores_input.update(&execution, ('b', 2), Float::new(0.5));
+    execution.commit();
+    assert_eq!(totals.read(&execution)[&('a', 1)], Float::new(1.0));
+    scores_input.update(&execution, ('a', 1), Float::new(-1.0));
+    execution.commit();
+    // 0.1 added ten times doesn't come to exactly 1.0, but the entry is still removed.
+    assert_eq!(
+        &*totals.read(&execution),
+        &vec![(('b', 2), Float::new(0.5))].into_iter().collect()
+    );
+    scores2_input.update(&execution, ('x', 7), Float::new(0.5));
+    probs_input.update(&execution, ('x', "heads"), Float::new(0.25));
+    execution.commit();
+    assert_eq!(
+        joined.read(&execution)[&('x', (7, "heads"))],
+        Float::new(0.125)
+    );
+
+    struct Coarse;
+    impl Tolerance for Coarse {
+        const EPSILON: f64 = 0.01;
+    }
+    let mut x = Float::<Coarse>::new(1.0);
+    x += Float::new(-0.995);
+    assert!(crate::monoid::Monoid::is_zero(&x));
+}
+
+#[test]
+fn test_threshold() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<char, isize>();
+    let rel = rel.split();
+    let positive = rel.clone().positive().get_dyn_arrangement(&creation);
+    let at_least = rel.clone().at_least(3).get_dyn_arrangement(&creation);
+    let capped = rel
+        .threshold(|r: &isize| (*r).min(2))
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    input.update(&execution, 'a', 2);
+    input.update(&execution, 'b', -1);
+    input.update(&execution, 'c', 4);
+    execution.commit();
+    assert_eq!(
+        &*positive.read(&execution),
+        &vec![('a', 1), ('c', 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*at_least.read(&execution),
+        &vec![('c', 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*capped.read(&execution),
+        &vec![('a', 2), ('b', -1), ('c', 2)].into_iter().collect()
+    );
+    input.update(&execution, 'a', 1);
+    input.update(&execution, 'b', 1);
+    input.update(&execution, 'c', -4);
+    execution.commit();
+    assert_eq!(
+        &*positive.read(&execution),
+        &vec![('a', 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*at_least.read(&execution),
+        &vec![('a', 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*capped.read(&execution),
+        &vec![('a', 2)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_product() {
+    let creation = CreationContext::new();
+    let (left_input, left) = creation.create_input::<char, isize>();
+    let (right_input, right) = creation.create_input::<usize, isize>();
+    let out = left.product(right).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    left_input.update(&execution, 'a', 1);
+    left_input.update(&execution, 'b', 2);
+    right_input.update(&execution, 1, 3);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![(('a', 1), 3), (('b', 1), 6)].into_iter().collect()
+    );
+    left_input.update(&execution, 'a', -1);
+    left_input.update(&execution, 'c', 1);
+    left_input.update(&execution, 'c', -1);
+    right_input.update(&execution, 2, 1);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![(('b', 1), 6), (('b', 2), 2)].into_iter().collect()
+    );
+    right_input.update(&execution, 1, -3);
+    right_input.update(&execution, 2, -1);
+    execution.commit();
+    assert!(out.read(&execution).is_empty());
+}
+
+#[test]
+fn test_range_joins() {
+    let creation = CreationContext::new();
+    let (events_input, events) = creation.create_input::<(i64, char), isize>();
+    let (readings_input, readings) = creation.create_input::<(i64, &str), isize>();
+    let (shifts_input, shifts) = creation.create_input::<((i64, i64), &str), isize>();
+    let events = events.split();
+    let band = events
+        .clone()
+        .band_join(readings, 1, 2)
+        .get_dyn_arrangement(&creation);
+    let intervals = events.interval_join(shifts).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    events_input.update(&execution, (10, 'a'), 1);
+    readings_input.update(&execution, (8, "early"), 1);
+    readings_input.update(&execution, (9, "near"), 1);
+    readings_input.update(&execution, (12, "late"), 1);
+    shifts_input.update(&execution, ((0, 10), "night"), 1);
+    shifts_input.update(&execution, ((10, 20), "day"), 1);
+    execution.commit();
+    assert_eq!(
+        &*band.read(&execution),
+        &vec![
+            (((10, 'a'), (9, "near")), 1),
+            (((10, 'a'), (12, "late")), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert_eq!(
+        &*intervals.read(&execution),
+        &vec![(((10, 'a'), ((10, 20), "day")), 1)]
+            .into_iter()
+            .collect()
+    );
+    events_input.update(&execution, (10, 'a'), -1);
+    events_input.update(&execution, (5, 'b'), 1);
+    readings_input.update(&execution, (4, "before"), 1);
+    shifts_input.update(&execution, ((3, 6), "break"), 2);
+    execution.commit();
+    assert_eq!(
+        &*band.read(&execution),
+        &vec![(((5, 'b'), (4, "before")), 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*intervals.read(&execution),
+        &vec![
+            (((5, 'b'), ((0, 10), "night")), 1),
+            (((5, 'b'), ((3, 6), "break")), 2)
+        ]
+        .into_iter()
+        .collect()
+    );
+
+    // Bounds near the ends of the key type are clamped rather than overflowing.
+    let creation = CreationContext::new();
+    let (left_input, left) = creation.create_input::<(u64, char), isize>();
+    let (right_input, right) = creation.create_input::<(u64, char), isize>();
+    let band = left.band_join(right, 1, 1).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    left_input.update(&execution, (0, 'a'), 1);
+    left_input.update(&execution, (u64::MAX, 'b'), 1);
+    right_input.update(&execution, (1, 'c'), 1);
+    right_input.update(&execution, (u64::MAX - 1, 'd'), 1);
+    execution.commit();
+    right_input.update(&execution, (0, 'e'), 1);
+    left_input.update(&execution, (u64::MAX - 1, 'f'), 1);
+    execution.commit();
+    assert_eq!(
+        &*band.read(&execution),
+        &vec![
+            (((0, 'a'), (1, 'c')), 1),
+            (((0, 'a'), (0, 'e')), 1),
+            (((u64::MAX, 'b'), (u64::MAX - 1, 'd')), 1),
+            (((u64::MAX - 1, 'f'), (u64::MAX - 1, 'd')), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+#[test]
+fn test_join_on() {
+    let creation = CreationContext::new();
+    let (people_input, people) = creation.create_input::<(&str, u32), isize>();
+    let (cities_input, cities) = creation.create_input::<(u32, &str), isize>();
+    let out = people
+        .join_on(cities, |&(_, city)| city, |&(id, _)| id)
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    people_input.update(&execution, ("alice", 1), 1);
+    people_input.update(&execution, ("bob", 2), 1);
+    cities_input.update(&execution, (1, "paris"), 1);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![((("alice", 1), (1, "paris")), 1)]
+            .into_iter()
+            .collect()
+    );
+    people_input.update(&execution, ("alice", 1), -1);
+    cities_input.update(&execution, (2, "rome"), 2);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![((("bob", 2), (2, "rome")), 2)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_group_rank() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<(char, u32), isize>();
+    let rel = rel.split();
+    let ranks = rel.clone().group_rank().get_dyn_arrangement(&creation);
+    let rows = rel.group_row_number().get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    input.update(&execution, ('a', 10), 1);
+    input.update(&execution, ('a', 20), 2);
+    input.update(&execution, ('a', 30), 1);
+    input.update(&execution, ('b', 5), 1);
+    execution.commit();
+    assert_eq!(
+        &*ranks.read(&execution),
+        &vec![
+            (('a', (10, 1)), 1),
+            (('a', (20, 2)), 2),
+            (('a', (30, 4)), 1),
+            (('b', (5, 1)), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert_eq!(
+        &*rows.read(&execution),
+        &vec![
+            (('a', (10, 1)), 1),
+            (('a', (20, 2)), 1),
+            (('a', (20, 3)), 1),
+            (('a', (30, 4)), 1),
+            (('b', (5, 1)), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    input.update(&execution, ('a', 15), 1);
+    input.update(&execution, ('a', 10), -1);
+    input.update(&execution, ('b', 5), -1);
+    execution.commit();
+    assert_eq!(
+        &*ranks.read(&execution),
+        &vec![
+            (('a', (15, 1)), 1),
+            (('a', (20, 2)), 2),
+            (('a', (30, 4)), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+    assert_eq!(
+        &*rows.read(&execution),
+        &vec![
+            (('a', (15, 1)), 1),
+            (('a', (20, 2)), 1),
+            (('a', (20, 3)), 1),
+            (('a', (30, 4)), 1)
+        ]
+        .into_iter()
+        .collect()
+    );
+}
+
+#[test]
+fn test_group_min_by_key() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<(char, (&str, u32)), isize>();
+    let rel = rel.split();
+    let (cheapest, cheapest_output) = rel
+        .clone()
+        .group_min_by_key(|&(_, cost)| cost)
+        .split_reduce_output(&creation);
+    let cheapest = cheapest.get_dyn_arrangement(&creation);
+    let dearest = rel
+        .group_max_by_key(|&(_, cost)| cost)
+        .get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    input.update(&execution, ('a', ("x", 3)), 1);
+    input.update(&execution, ('a', ("y", 1)), 1);
+    input.update(&execution, ('a', ("z", 1)), 1);
+    input.update(&execution, ('a', ("w", 3)), 1);
+    execution.commit();
+    assert_eq!(cheapest_output.read(&execution)[&'a'].0, ("y", 1));
+    assert_eq!(
+        &*dearest.read(&execution),
+        &vec![(('a', ("x", 3)), 1)].into_iter().collect()
+    );
+    input.update(&execution, ('a', ("y", 1)), -1);
+    input.update(&execution, ('a', ("x", 3)), -1);
+    execution.commit();
+    assert_eq!(
+        &*cheapest.read(&execution),
+        &vec![(('a', ("z", 1)), 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*dearest.read(&execution),
+        &vec![(('a', ("w", 3)), 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_group_count_distinct() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<(char, u32), isize>();
+    let out = rel.group_count_distinct().get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    input.update(&execution, ('a', 1), 1);
+    input.update(&execution, ('a', 1), 1);
+    input.update(&execution, ('a', 2), 1);
+    input.update(&execution, ('b', 7), 3);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![(('a', 2), 1), (('b', 1), 1)].into_iter().collect()
+    );
+    input.update(&execution, ('a', 1), -1);
+    input.update(&execution, ('b', 7), -3);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![(('a', 2), 1)].into_iter().collect()
+    );
+    input.update(&execution, ('a', 1), -1);
+    input.update(&execution, ('a', 3), 1);
+    input.update(&execution, ('a', 4), 1);
+    execution.commit();
+    assert_eq!(
+        &*out.read(&execution),
+        &vec![(('a', 3), 1)].into_iter().collect()
+    );
+}
+
+#[test]
+fn test_partition() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<u32, isize>();
+    let rel = rel.split();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls2 = Arc::clone(&calls);
+    let parts = rel
+        .clone()
+        .partition(3, move |x| {
+            calls2.fetch_add(1, Ordering::SeqCst);
+            (x % 3) as usize
+        })
+        .into_iter()
+        .map(|part| part.get_dyn_arrangement(&creation))
+        .collect::<Vec<_>>();
+    let (evens, odds) = rel.partition_by(|x| x % 2 == 0);
+    let evens = evens.get_dyn_arrangement(&creation);
+    let odds = odds.get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    for x in 0..6 {
+        input.update(&execution, x, 1);
+    }
+    execution.commit();
+    for (i, part) in parts.iter().enumerate() {
+        assert_eq!(
+            &*part.read(&execution),
+            &vec![(i as u32, 1), (i as u32 + 3, 1)].into_iter().collect()
+        );
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 6);
+    assert_eq!(
+        &*evens.read(&execution),
+        &vec![(0, 1), (2, 1), (4, 1)].into_iter().collect()
+    );
+    input.update(&execution, 4, -1);
+    input.update(&execution, 7, 1);
+    execution.commit();
+    assert_eq!(
+        &*parts[1].read(&execution),
+        &vec![(1, 1), (7, 1)].into_iter().collect()
+    );
+    assert_eq!(calls.load(Ordering::SeqCst), 8);
+    assert_eq!(
+        &*odds.read(&execution),
+        &vec![(1, 1), (3, 1), (5, 1), (7, 1)].into_iter().collect()
+    );
+    assert!(std::panic::catch_unwind(|| {
+        let creation = CreationContext::new();
+        let (_, rel) = creation.create_input::<u32, isize>();
+        rel.partition(0, |_| 0)
+    })
+    .is_err());
+}
+
+#[cfg(feature = "deterministic")]
+#[test]
+fn test_deterministic_order() {
+    use std::process::Command;
+    use std::sync::{Arc, Mutex};
+
+    // Runs a graph whose operators all iterate over hashed state and logs the order in which
+    // records come out of it.
+    fn run() -> Vec<(u32, u32)> {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let creation = CreationContext::new();
+        let (input, rel) = creation.create_tracked_input::<(u32, u32), isize>();
+        let rel = rel.split();
+        let offsets: StaticTable<u32, u32> =
+            (0..7).flat_map(|k| (0..10).map(move |v| (k, v))).collect();
+        let logger = Arc::clone(&log);
+        let outp = rel
+            .clone()
+            .join(rel.map(|(k, x)| (k, x * 2)))
+            .map(|(k, (x, y))| (k, x + y))
+            .join_static(&offsets)
+            .map(|(k, (x, v))| (k, x + v))
+            .distinct()
+            .map(move |x| {
+                logger.lock().unwrap().push(x);
+                x
+            })
+            .get_dyn_arrangement(&creation);
+        let mut execution = creation.begin();
+        for x in 0..50 {
+            input.insert(&execution, (x % 7, x));
+        }
+        execution.commit();
+        outp.read(&execution);
+        input.replace_all(&execution, (20..80).map(|x| ((x % 5, x), 1)));
+        execution.commit();
+        outp.read(&execution);
+        let result = log.lock().unwrap().clone();
+        result
+    }
+
+    const CHILD: &str = "DC2_DETERMINISTIC_ORDER_CHILD";
+    if std::env::var_os(CHILD).is_some() {
+        println!("order: {:?}", run());
+        return;
+    }
+    let order = run();
+    assert_eq!(order, run());
+    // A separate process gets a fresh random state, so this catches anything still seeded from it.
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["tests::test_deterministic_order", "--exact", "--nocapture"])
+        .env(CHILD, "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let expected = format!("order: {:?}", order);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(&expected));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_send_to_thread() {
+    let creation = CreationContext::new();
+    let (input, rel) = creation.create_input::<usize, isize>();
+    let outp = rel.map(|x| x * 2).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    let worker = std::thread::spawn(move || {
+        input.insert(&execution, 3);
+        execution.commit();
+        let result = outp.read(&execution).clone();
+        result
+    });
+    assert_eq!(worker.join().unwrap(), vec![(6, 1)].into_iter().collect());
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_flush_parallel() {
+    let creation = CreationContext::new();
+    let (input1, rel1) = creation.create_input::<usize, isize>();
+    let (input2, rel2) = creation.create_input::<usize, isize>();
+    let rel1 = rel1.split();
+    let doubled = rel1.clone().map(|x| x * 2).get_dyn_arrangement(&creation);
+    let tripled = rel1.map(|x| x * 3).get_dyn_arrangement(&creation);
+    let other = rel2.map(|x| x + 1).get_dyn_arrangement(&creation);
+    let mut execution = creation.begin();
+    input1.insert(&execution, 1);
+    input2.insert(&execution, 10);
+    execution.commit();
+    execution.flush_parallel(&[&doubled, &tripled, &other], 4);
+    assert_eq!(
+        &*doubled.read(&execution),
+        &vec![(2, 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*tripled.read(&execution),
+        &vec![(3, 1)].into_iter().collect()
+    );
+    assert_eq!(
+        &*other.read(&execution),
+        &vec![(11, 1)].into_iter().collect()
+    );
+}
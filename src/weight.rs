@@ -0,0 +1,332 @@
+//! Multiplicity types other than plain integers. Rust doesn't allow arithmetic operators to be
+//! implemented on tuples or `Option`s, so the product and optional monoids are newtypes.
+//! Floating-point weights are `Float`s, which treat anything close enough to zero as zero.
+
+use crate::emptyable::Emptyable;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+use std::ops::{AddAssign, Mul, Neg, Sub};
+
+/// The product of two monoids: addition (and multiplication) are componentwise, and the pair is
+/// zero only when both components are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Pair<A, B>(pub A, pub B);
+
+impl<A: AddAssign, B: AddAssign> AddAssign for Pair<A, B> {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+        self.1 += other.1;
+    }
+}
+
+impl<A: Sub<Output = A>, B: Sub<Output = B>> Sub for Pair<A, B> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Pair(self.0 - other.0, self.1 - other.1)
+    }
+}
+
+impl<A: Neg<Output = A>, B: Neg<Output = B>> Neg for Pair<A, B> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Pair(-self.0, -self.1)
+    }
+}
+
+impl<A: Emptyable, B: Emptyable> Emptyable for Pair<A, B> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty() && self.1.is_empty()
+    }
+}
+
+impl<A: Mul<A2>, B: Mul<B2>, A2, B2> Mul<Pair<A2, B2>> for Pair<A, B> {
+    type Output = Pair<A::Output, B::Output>;
+    fn mul(self, other: Pair<A2, B2>) -> Self::Output {
+        Pair(self.0 * other.0, self.1 * other.1)
+    }
+}
+
+/// A monoid extended with an explicit absent value, which is its zero. Present values are
+/// normalized to `None` when they sum to zero, and multiplying by an absent value gives an absent
+/// value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Optional<R>(pub Option<R>);
+
+impl<R> Default for Optional<R> {
+    fn default() -> Self {
+        Optional(None)
+    }
+}
+
+impl<R: Emptyable> Optional<R> {
+    pub fn new(r: R) -> Self {
+        Optional(if r.is_empty() { None } else { Some(r) })
+    }
+}
+
+impl<R: Emptyable + AddAssign> AddAssign for Optional<R> {
+    fn add_assign(&mut self, other: Self) {
+        if let Some(o) = other.0 {
+            *self = Optional::new(match self.0.take() {
+                None => o,
+                Some(mut s) => {
+                    s += o;
+                    s
+                }
+            })
+        }
+    }
+}
+
+impl<R: Emptyable + AddAssign + Neg<Output = R>> Sub for Optional<R> {
+    type Output = Self;
+    fn sub(mut self, other: Self) -> Self {
+        self += -other;
+        self
+    }
+}
+
+impl<R: Neg<Output = R>> Neg for Optional<R> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Optional(self.0.map(Neg::neg))
+    }
+}
+
+impl<R: Emptyable> Emptyable for Optional<R> {
+    fn is_empty(&self) -> bool {
+        self.0.as_ref().is_none_or(Emptyable::is_empty)
+    }
+}
+
+impl<R: Mul<R2>, R2> Mul<Optional<R2>> for Optional<R> {
+    type Output = Optional<R::Output>;
+    fn mul(self, other: Optional<R2>) -> Self::Output {
+        match (self.0, other.0) {
+            (Some(l), Some(r)) => Optional(Some(l * r)),
+            _ => Optional(None),
+        }
+    }
+}
+
+/// A fixed-point decimal with `DECIMALS` digits after the point, stored as an `i64` count of
+/// `10^-DECIMALS` units. Addition is exact. Multiplication rounds half away from zero, so it
+/// isn't distributive over addition: multiplying a sum of weights can differ in the last digit
+/// from summing their products. The weights of a join's output therefore depend on how its
+/// inputs were batched, and a retraction may not exactly cancel the insertion it undoes unless
+/// both sides carry the same weights as before. All operations panic on overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fixed<const DECIMALS: u32>(i64);
+
+impl<const DECIMALS: u32> Fixed<DECIMALS> {
+    const SCALE: i64 = 10i64.pow(DECIMALS);
+
+    /// The decimal `units * 10^-DECIMALS`.
+    pub fn from_units(units: i64) -> Self {
+        Fixed(units)
+    }
+    pub fn from_int(n: i64) -> Self {
+        Fixed(n.checked_mul(Self::SCALE).expect("Fixed overflow"))
+    }
+    pub fn units(self) -> i64 {
+        self.0
+    }
+}
+
+impl<const DECIMALS: u32> AddAssign for Fixed<DECIMALS> {
+    fn add_assign(&mut self, other: Self) {
+        self.0 = self.0.checked_add(other.0).expect("Fixed overflow");
+    }
+}
+
+impl<const DECIMALS: u32> Sub for Fixed<DECIMALS> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Fixed(self.0.checked_sub(other.0).expect("Fixed overflow"))
+    }
+}
+
+impl<const DECIMALS: u32> Neg for Fixed<DECIMALS> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Fixed(self.0.checked_neg().expect("Fixed overflow"))
+    }
+}
+
+impl<const DECIMALS: u32> Emptyable for Fixed<DECIMALS> {
+    fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl<const DECIMALS: u32> Mul for Fixed<DECIMALS> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        let product = self.0 as i128 * other.0 as i128;
+        let scale = Self::SCALE as i128;
+        let half = if product < 0 { -scale / 2 } else { scale / 2 };
+        Fixed(i64::try_from((product + half) / scale).expect("Fixed overflow"))
+    }
+}
+
+impl<const DECIMALS: u32> Display for Fixed<DECIMALS> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let scale = Self::SCALE as u64;
+        if DECIMALS == 0 {
+            write!(f, "{}{}", sign, abs)
+        } else {
+            write!(
+                f,
+                "{}{}.{:0width$}",
+                sign,
+                abs / scale,
+                abs % scale,
+                width = DECIMALS as usize
+            )
+        }
+    }
+}
+
+/// The zero threshold for a `Float`.
+pub trait Tolerance: 'static {
+    /// Weights whose absolute value is at most this are treated as zero, so the records carrying
+    /// them are dropped from maps and arrangements.
+    const EPSILON: f64;
+}
+
+/// The default tolerance of `1e-9`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultTolerance;
+
+impl Tolerance for DefaultTolerance {
+    const EPSILON: f64 = 1e-9;
+}
+
+/// A floating-point weight, such as a probability or score. Sums are compensated (using
+/// Neumaier's variant of Kahan summation) so that rounding errors don't accumulate over many
+/// updates, and anything within `T::EPSILON` of zero counts as zero. Equality is also up to
+/// `T::EPSILON`.
+pub struct Float<T = DefaultTolerance> {
+    sum: f64,
+    compensation: f64,
+    tolerance: PhantomData<T>,
+}
+
+impl<T> Float<T> {
+    pub fn new(value: f64) -> Self {
+        Float {
+            sum: value,
+            compensation: 0.0,
+            tolerance: PhantomData,
+        }
+    }
+    pub fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+impl<T> Clone for Float<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Float<T> {}
+
+impl<T> Default for Float<T> {
+    fn default() -> Self {
+        Float::new(0.0)
+    }
+}
+
+impl<T> fmt::Debug for Float<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.value(), f)
+    }
+}
+
+impl<T> Display for Float<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.value(), f)
+    }
+}
+
+impl<T: Tolerance> PartialEq for Float<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.value() - other.value()).abs() <= T::EPSILON
+    }
+}
+
+impl<T: Tolerance> PartialOrd for Float<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else {
+            self.value().partial_cmp(&other.value())
+        }
+    }
+}
+
+impl<T> AddAssign for Float<T> {
+    fn add_assign(&mut self, other: Self) {
+        for x in [other.sum, other.compensation] {
+            let sum = self.sum + x;
+            if self.sum.abs() >= x.abs() {
+                self.compensation += (self.sum - sum) + x;
+            } else {
+                self.compensation += (x - sum) + self.sum;
+            }
+            self.sum = sum;
+        }
+    }
+}
+
+impl<T> Neg for Float<T> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Float {
+            sum: -self.sum,
+            compensation: -self.compensation,
+            tolerance: PhantomData,
+        }
+    }
+}
+
+impl<T> Sub for Float<T> {
+    type Output = Self;
+    fn sub(mut self, other: Self) -> Self {
+        self += -other;
+        self
+    }
+}
+
+impl<T: Tolerance> Emptyable for Float<T> {
+    fn is_empty(&self) -> bool {
+        self.value().abs() <= T::EPSILON
+    }
+}
+
+impl<T> Mul for Float<T> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Float::new(self.value() * other.value())
+    }
+}
+
+impl<T> Mul<isize> for Float<T> {
+    type Output = Self;
+    fn mul(self, other: isize) -> Self {
+        Float::new(self.value() * other as f64)
+    }
+}
+
+impl<T> Mul<Float<T>> for isize {
+    type Output = Float<T>;
+    fn mul(self, other: Float<T>) -> Float<T> {
+        other * self
+    }
+}